@@ -1,7 +1,9 @@
 use bevy::{core_pipeline::bloom::BloomSettings, prelude::*};
 
+use crate::fps_camera::PlayerView;
+
 pub fn update_bloom_settings(
-    mut camera: Query<(Entity, Option<&mut BloomSettings>), With<Camera>>,
+    mut camera: Query<(Entity, Option<&mut BloomSettings>), With<PlayerView>>,
     _text: Query<&mut Text>,
     _commands: Commands,
     _keycode: Res<Input<KeyCode>>,
@@ -0,0 +1,232 @@
+use std::ops::Add;
+
+use bevy::prelude::*;
+
+use crate::{
+    fps_camera::FPSCamera,
+    fps_movement::{ease_bob_intensity, horizontal_speed, FPSMovement, PlayerLanded},
+};
+
+// sent by anything that should shake the view, taking damage for one
+pub struct CameraTrauma(pub f32);
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CameraOffset {
+    pub translation: Vec3,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub roll: f32,
+}
+
+impl Add for CameraOffset {
+    type Output = CameraOffset;
+    fn add(self, other: CameraOffset) -> CameraOffset {
+        CameraOffset {
+            translation: self.translation + other.translation,
+            pitch: self.pitch + other.pitch,
+            yaw: self.yaw + other.yaw,
+            roll: self.roll + other.roll,
+        }
+    }
+}
+
+pub trait CameraEffect {
+    fn offset(&self) -> CameraOffset;
+}
+
+pub struct HeadBob {
+    pub amplitude: Vec2,
+    // bob cycles per meter travelled
+    pub frequency: f32,
+    pub phase: f32,
+    pub intensity: f32,
+}
+
+impl CameraEffect for HeadBob {
+    fn offset(&self) -> CameraOffset {
+        // side to side once per stride, up and down once per step
+        CameraOffset {
+            translation: Vec3::new(
+                f32::sin(self.phase) * self.amplitude.x,
+                -f32::abs(f32::sin(self.phase)) * self.amplitude.y,
+                0.,
+            ) * self.intensity,
+            roll: f32::sin(self.phase) * self.amplitude.x * 0.5 * self.intensity,
+            ..default()
+        }
+    }
+}
+
+pub struct LookSway {
+    // roll in radians per radian per second of turning
+    pub roll_factor: f32,
+    pub max_roll: f32,
+    pub roll: f32,
+}
+
+impl CameraEffect for LookSway {
+    fn offset(&self) -> CameraOffset {
+        CameraOffset {
+            roll: self.roll,
+            ..default()
+        }
+    }
+}
+
+pub struct LandingDip {
+    pub dip: f32,
+    pub recovery: f32,
+}
+
+impl CameraEffect for LandingDip {
+    fn offset(&self) -> CameraOffset {
+        CameraOffset {
+            pitch: -self.dip,
+            ..default()
+        }
+    }
+}
+
+pub struct TraumaShake {
+    pub trauma: f32,
+    // trauma lost per second
+    pub decay: f32,
+    pub max_angle: Vec3,
+    pub frequency: f32,
+    pub time: f32,
+}
+
+impl CameraEffect for TraumaShake {
+    fn offset(&self) -> CameraOffset {
+        // squaring trauma keeps small hits subtle and big ones violent
+        let shake = self.trauma * self.trauma;
+        let t = self.time * self.frequency;
+        CameraOffset {
+            pitch: perlin_1d(t, 1) * self.max_angle.x * shake,
+            yaw: perlin_1d(t, 2) * self.max_angle.y * shake,
+            roll: perlin_1d(t, 3) * self.max_angle.z * shake,
+            ..default()
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct CameraEffects {
+    // turned off for motion sensitive players
+    pub enabled: bool,
+    pub head_bob: HeadBob,
+    pub sway: LookSway,
+    pub landing_dip: LandingDip,
+    pub trauma: TraumaShake,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        CameraEffects {
+            enabled: true,
+            head_bob: HeadBob {
+                amplitude: Vec2::new(0.025, 0.035),
+                frequency: 1.4,
+                phase: 0.,
+                intensity: 0.,
+            },
+            sway: LookSway {
+                roll_factor: 0.004,
+                max_roll: 2_f32.to_radians(),
+                roll: 0.,
+            },
+            landing_dip: LandingDip {
+                dip: 0.,
+                recovery: 8.,
+            },
+            trauma: TraumaShake {
+                trauma: 0.,
+                decay: 0.8,
+                max_angle: Vec3::new(4., 4., 6.) * (std::f32::consts::PI / 180.),
+                frequency: 18.,
+                time: 0.,
+            },
+        }
+    }
+}
+
+impl CameraEffects {
+    pub fn offset(&self) -> CameraOffset {
+        if !self.enabled {
+            return CameraOffset::default();
+        }
+        self.head_bob.offset()
+            + self.sway.offset()
+            + self.landing_dip.offset()
+            + self.trauma.offset()
+    }
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma.trauma = (self.trauma.trauma + amount).clamp(0., 1.);
+    }
+}
+
+fn hash_gradient(i: i32, seed: u32) -> f32 {
+    let mut x = (i as u32).wrapping_mul(0x9E37_79B1) ^ seed.wrapping_mul(0x85EB_CA77);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x2C1B_3C6D);
+    x ^= x >> 12;
+    x = x.wrapping_mul(0x297A_2D39);
+    x ^= x >> 15;
+    x as f32 / u32::MAX as f32 * 2. - 1.
+}
+
+// one dimensional perlin noise, roughly in -1..1, a different seed gives an unrelated curve
+pub fn perlin_1d(x: f32, seed: u32) -> f32 {
+    let cell = x.floor();
+    let t = x - cell;
+    let from_left = hash_gradient(cell as i32, seed) * t;
+    let from_right = hash_gradient(cell as i32 + 1, seed) * (t - 1.);
+    let fade = t * t * t * (t * (t * 6. - 15.) + 10.);
+    (from_left + (from_right - from_left) * fade) * 2.
+}
+
+pub fn apply_camera_events(
+    mut landed_events: EventReader<PlayerLanded>,
+    mut trauma_events: EventReader<CameraTrauma>,
+    mut effects_query: Query<&mut CameraEffects>,
+) {
+    for landed in landed_events.iter() {
+        let dip = f32::min(landed.impact_speed * 0.6, 8.).to_radians();
+        for mut effects in effects_query.iter_mut() {
+            effects.landing_dip.dip = effects.landing_dip.dip.max(dip);
+        }
+    }
+    for trauma in trauma_events.iter() {
+        for mut effects in effects_query.iter_mut() {
+            effects.add_trauma(trauma.0);
+        }
+    }
+}
+
+pub fn update_camera_effects(
+    time: Res<Time>,
+    mut effects_query: Query<(&mut CameraEffects, &FPSCamera, &FPSMovement)>,
+) {
+    let delta_seconds = time.delta_seconds();
+    if delta_seconds <= 0. {
+        return;
+    }
+    for (mut effects, camera, movement) in effects_query.iter_mut() {
+        let bob = &mut effects.head_bob;
+        bob.intensity = ease_bob_intensity(bob.intensity, movement, 1., delta_seconds);
+        bob.phase +=
+            horizontal_speed(movement) * bob.frequency * std::f32::consts::PI * delta_seconds;
+
+        let sway = &mut effects.sway;
+        let target_roll =
+            (camera.look_speed.y * sway.roll_factor).clamp(-sway.max_roll, sway.max_roll);
+        sway.roll += (target_roll - sway.roll) * f32::min(delta_seconds * 8., 1.);
+
+        let dip = &mut effects.landing_dip;
+        dip.dip *= 1. - f32::min(delta_seconds * dip.recovery, 1.);
+
+        let trauma = &mut effects.trauma;
+        trauma.trauma = f32::max(trauma.trauma - trauma.decay * delta_seconds, 0.);
+        trauma.time += delta_seconds;
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{asset::FileAssetIo, prelude::*};
+use serde::{de::DeserializeOwned, Serialize};
+
+pub fn data_file_path(file_name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("fps_game").join(file_name))
+}
+
+pub fn config_file_path(file_name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("fps_game").join(file_name))
+}
+
+pub fn asset_file_path(relative_path: &str) -> PathBuf {
+    FileAssetIo::get_base_path().join("assets").join(relative_path)
+}
+
+// files shipped with the game rather than written by the player, so a missing or broken one is
+// an error for the loading screen instead of a quiet fall back to defaults
+pub fn load_asset_toml<T: DeserializeOwned>(relative_path: &str) -> Result<T, String> {
+    let path = asset_file_path(relative_path);
+    let contents = fs::read_to_string(&path)
+        .map_err(|err| format!("could not read {}: {}", relative_path, err))?;
+    toml::from_str(&contents).map_err(|err| format!("could not parse {}: {}", relative_path, err))
+}
+
+pub fn load_toml<T: DeserializeOwned + Default>(path: &Path) -> T {
+    match fs::read_to_string(path) {
+        Ok(contents) => match toml::from_str::<T>(&contents) {
+            Ok(value) => value,
+            Err(err) => {
+                // keep the broken file around instead of overwriting it on the next save
+                let backup = path.with_extension("toml.corrupt");
+                warn!(
+                    "{:?} is corrupt ({}), moving it to {:?} and using defaults",
+                    path, err, backup
+                );
+                if let Err(err) = fs::rename(path, &backup) {
+                    warn!("failed to back up {:?}: {}", path, err);
+                }
+                T::default()
+            }
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => T::default(),
+        Err(err) => {
+            warn!("failed to read {:?}: {}", path, err);
+            T::default()
+        }
+    }
+}
+
+pub fn save_toml<T: Serialize>(path: &Path, value: &T) {
+    let contents = match toml::to_string_pretty(value) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warn!("failed to serialize {:?}: {}", path, err);
+            return;
+        }
+    };
+    if let Some(parent) = path.parent() {
+        if let Err(err) = fs::create_dir_all(parent) {
+            warn!("failed to create {:?}: {}", parent, err);
+            return;
+        }
+    }
+    // write next to the real file and swap it in so a crash mid-write can't corrupt it
+    let temp_path = path.with_extension("toml.tmp");
+    if let Err(err) = fs::write(&temp_path, contents) {
+        warn!("failed to write {:?}: {}", temp_path, err);
+        return;
+    }
+    if let Err(err) = fs::rename(&temp_path, path) {
+        warn!("failed to replace {:?}: {}", path, err);
+    }
+}
@@ -0,0 +1,295 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use crate::{
+    input_actions::{Action, Binding},
+    menu::{spawn_button, MenuButton},
+    settings::Settings,
+};
+
+// split up like the settings so every page fits with the ui scaled up
+pub const CONTROL_PAGES: [(&str, &[Action]); 4] = [
+    (
+        "MOVEMENT",
+        &[
+            Action::MoveForward,
+            Action::MoveBack,
+            Action::MoveLeft,
+            Action::MoveRight,
+            Action::Jump,
+            Action::Crouch,
+            Action::Sprint,
+            Action::LeanLeft,
+            Action::LeanRight,
+        ],
+    ),
+    (
+        "WEAPON",
+        &[
+            Action::Fire,
+            Action::Aim,
+            Action::Reload,
+            Action::SwitchWeapon,
+            Action::FireMode,
+        ],
+    ),
+    (
+        "GENERAL",
+        &[
+            Action::Pause,
+            Action::ToggleCursorLock,
+            Action::Spectate,
+            Action::SpectatorNext,
+            Action::SpectatorPrevious,
+            Action::SpectatorMode,
+        ],
+    ),
+    (
+        "EDITOR",
+        &[
+            Action::ToggleRecoilEditor,
+            Action::EditPattern,
+            Action::RecordPattern,
+            Action::PracticePattern,
+            Action::SavePattern,
+        ],
+    ),
+];
+
+#[derive(Resource, Default)]
+pub struct ControlsMenu {
+    pub open: bool,
+    pub page: usize,
+    pub waiting_for: Option<Action>,
+}
+
+#[derive(Component)]
+pub struct ControlsScreen {
+    pub page: usize,
+}
+
+#[derive(Component)]
+pub struct ControlsPageButton(pub usize);
+
+#[derive(Component)]
+pub struct RebindButton(pub Action);
+
+#[derive(Component)]
+pub struct BindingText(pub Action);
+
+pub fn binding_text(settings: &Settings, controls_menu: &ControlsMenu, action: Action) -> String {
+    if controls_menu.waiting_for == Some(action) {
+        return "PRESS A KEY, ESCAPE TO CANCEL".to_string();
+    }
+    let labels: Vec<String> = settings
+        .bindings
+        .bindings(action)
+        .iter()
+        .map(|binding| binding.label())
+        .collect();
+    if labels.is_empty() {
+        return "UNBOUND".to_string();
+    }
+    labels.join(" / ")
+}
+
+pub fn update_controls_screen(
+    mut commands: Commands,
+    controls_menu: Res<ControlsMenu>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    screen_query: Query<(Entity, &ControlsScreen)>,
+    mut binding_query: Query<(&BindingText, &mut Text)>,
+) {
+    if settings.is_changed() || controls_menu.is_changed() {
+        for (binding_text_marker, mut text) in binding_query.iter_mut() {
+            text.sections[0].value = binding_text(&settings, &controls_menu, binding_text_marker.0);
+        }
+    }
+    let spawned_page = screen_query.iter().next().map(|(_, screen)| screen.page);
+    let page = controls_menu.page.min(CONTROL_PAGES.len() - 1);
+    let wanted_page = controls_menu.open.then_some(page);
+    if spawned_page == wanted_page {
+        return;
+    }
+    for (entity, _) in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !controls_menu.open {
+        return;
+    }
+    let font = asset_server.load("font.ttf");
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.05, 0.05, 0.05).into(),
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            ControlsScreen { page },
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::bottom(Val::Px(10.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|tabs| {
+                    for (index, (label, _)) in CONTROL_PAGES.iter().enumerate() {
+                        let color = if index == page {
+                            Color::rgb(0.3, 0.3, 0.3)
+                        } else {
+                            Color::rgb(0.15, 0.15, 0.15)
+                        };
+                        tabs.spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(150.0), Val::Px(36.0)),
+                                    margin: UiRect::horizontal(Val::Px(4.0)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                background_color: color.into(),
+                                ..default()
+                            },
+                            ControlsPageButton(index),
+                        ))
+                        .with_children(|tab| {
+                            tab.spawn(TextBundle::from_section(*label, text_style.clone()));
+                        });
+                    }
+                });
+            for action in CONTROL_PAGES[page].1.iter().copied() {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(760.0), Val::Px(36.0)),
+                            margin: UiRect::all(Val::Px(3.0)),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn(
+                            TextBundle::from_section(action.label(), text_style.clone())
+                                .with_style(Style {
+                                    size: Size::new(Val::Px(260.0), Val::Auto),
+                                    ..default()
+                                }),
+                        );
+                        row.spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(500.0), Val::Px(34.0)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                                ..default()
+                            },
+                            RebindButton(action),
+                        ))
+                        .with_children(|button| {
+                            button.spawn((
+                                TextBundle::from_section(
+                                    binding_text(&settings, &controls_menu, action),
+                                    text_style.clone(),
+                                ),
+                                BindingText(action),
+                            ));
+                        });
+                    });
+            }
+            spawn_button(parent, font, "BACK", MenuButton::CloseControls);
+        });
+}
+
+pub fn handle_controls_page_buttons(
+    interaction_query: Query<(&Interaction, &ControlsPageButton), Changed<Interaction>>,
+    mut controls_menu: ResMut<ControlsMenu>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Clicked && controls_menu.page != button.0 {
+            controls_menu.page = button.0;
+            controls_menu.waiting_for = None;
+        }
+    }
+}
+
+pub fn handle_rebind_buttons(
+    interaction_query: Query<(&Interaction, &RebindButton), Changed<Interaction>>,
+    mut controls_menu: ResMut<ControlsMenu>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            controls_menu.waiting_for = Some(button.0);
+        }
+    }
+}
+
+pub fn capture_rebind(
+    mut controls_menu: ResMut<ControlsMenu>,
+    mut settings: ResMut<Settings>,
+    key: Res<Input<KeyCode>>,
+    mouse_button: Res<Input<MouseButton>>,
+    gamepad_button: Res<Input<GamepadButton>>,
+) {
+    // the click that started the rebind is still just_pressed on the frame it was set
+    if controls_menu.is_changed() {
+        return;
+    }
+    let Some(action) = controls_menu.waiting_for else {
+        return;
+    };
+    if key.just_pressed(KeyCode::Escape) {
+        controls_menu.waiting_for = None;
+        return;
+    }
+    let binding = if let Some(code) = key.get_just_pressed().next() {
+        Binding::Key(*code)
+    } else if let Some(button) = mouse_button.get_just_pressed().next() {
+        Binding::Mouse(*button)
+    } else if let Some(button) = gamepad_button.get_just_pressed().next() {
+        Binding::Gamepad(button.button_type)
+    } else {
+        return;
+    };
+    settings.bindings.rebind(action, binding);
+    controls_menu.waiting_for = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_actions::ALL_ACTIONS;
+
+    #[test]
+    fn every_action_is_on_one_page() {
+        for action in ALL_ACTIONS {
+            let pages = CONTROL_PAGES
+                .iter()
+                .filter(|(_, actions)| actions.contains(&action))
+                .count();
+            assert_eq!(pages, 1, "{:?}", action);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fps_camera::PlayerView, fps_shooting::shot_spread, gun_control::GunController,
+    settings::Settings,
+};
+
+const SHARE_CODE_VERSION: u8 = 1;
+// crockford base32, no letters that are easily mistaken for digits
+const SHARE_CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+// sizes are in screen pixels, the ui scale doesn't touch them
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct CrosshairStyle {
+    pub length: f32,
+    pub gap: f32,
+    pub thickness: f32,
+    pub outline: f32,
+    pub dot: bool,
+    // opens the gap up with the spread of the next shot
+    pub dynamic: bool,
+    pub color: [u8; 4],
+}
+
+impl Default for CrosshairStyle {
+    fn default() -> Self {
+        CrosshairStyle {
+            length: 6.,
+            gap: 3.,
+            thickness: 2.,
+            outline: 1.,
+            dot: false,
+            dynamic: true,
+            color: [0, 255, 0, 255],
+        }
+    }
+}
+
+// sizes are shared in half pixels, which is as fine as anyone can see
+fn to_half_pixels(value: f32) -> u8 {
+    (value * 2.).round().clamp(0., 255.) as u8
+}
+
+fn from_half_pixels(value: u8) -> f32 {
+    value as f32 / 2.
+}
+
+impl CrosshairStyle {
+    pub fn share_code(&self) -> String {
+        let flags = self.dot as u8 | (self.dynamic as u8) << 1;
+        let bytes = [
+            SHARE_CODE_VERSION,
+            to_half_pixels(self.length),
+            // the gap can go negative to overlap the lines
+            (self.gap * 2.).round().clamp(-128., 127.) as i8 as u8,
+            to_half_pixels(self.thickness),
+            to_half_pixels(self.outline),
+            flags,
+            self.color[0],
+            self.color[1],
+            self.color[2],
+            self.color[3],
+        ];
+        encode_base32(&bytes)
+    }
+    pub fn from_share_code(code: &str) -> Option<Self> {
+        let bytes = decode_base32(code)?;
+        if bytes.len() < 10 || bytes[0] != SHARE_CODE_VERSION {
+            return None;
+        }
+        Some(CrosshairStyle {
+            length: from_half_pixels(bytes[1]),
+            gap: bytes[2] as i8 as f32 / 2.,
+            thickness: from_half_pixels(bytes[3]),
+            outline: from_half_pixels(bytes[4]),
+            dot: bytes[5] & 1 != 0,
+            dynamic: bytes[5] & 2 != 0,
+            color: [bytes[6], bytes[7], bytes[8], bytes[9]],
+        })
+    }
+}
+
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut code = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = buffer << 8 | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            code.push(SHARE_CODE_ALPHABET[(buffer >> bits & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        code.push(SHARE_CODE_ALPHABET[(buffer << (5 - bits) & 31) as usize] as char);
+    }
+    code
+}
+
+// forgiving about case, dashes and the letters crockford leaves out
+fn decode_base32(code: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in code.chars().filter(|c| !matches!(c, '-' | ' ')) {
+        let c = match c.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            c => c,
+        };
+        let value = SHARE_CODE_ALPHABET.iter().position(|a| *a as char == c)? as u32;
+        buffer = buffer << 5 | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[derive(Component)]
+pub struct Crosshair {
+    // pixels the gap is opened by spread, eased so it doesn't flicker between shots
+    pub spread: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CrosshairPart {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    Dot,
+}
+
+#[derive(Component)]
+pub struct CrosshairPiece {
+    pub part: CrosshairPart,
+    pub outline: bool,
+}
+
+const CROSSHAIR_PARTS: [CrosshairPart; 5] = [
+    CrosshairPart::Top,
+    CrosshairPart::Bottom,
+    CrosshairPart::Left,
+    CrosshairPart::Right,
+    CrosshairPart::Dot,
+];
+
+// fills the screen and centres a zero sized anchor, so the pieces stay in the middle whatever
+// the window does
+pub fn spawn_crosshair(parent: &mut ChildBuilder) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    position_type: PositionType::Absolute,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            Crosshair { spread: 0. },
+        ))
+        .with_children(|parent| {
+            parent.spawn(NodeBundle::default()).with_children(|anchor| {
+                // outlines first so the fill draws over them
+                for outline in [true, false] {
+                    for part in CROSSHAIR_PARTS {
+                        anchor.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            CrosshairPiece { part, outline },
+                        ));
+                    }
+                }
+            });
+        });
+}
+
+// top left corner and size of a piece, relative to the centre of the screen
+pub fn piece_rect(part: CrosshairPart, style: &CrosshairStyle, gap: f32) -> (Vec2, Vec2) {
+    let thickness = style.thickness;
+    let length = style.length;
+    let half = thickness / 2.;
+    match part {
+        CrosshairPart::Top => (Vec2::new(-half, -(gap + length)), Vec2::new(thickness, length)),
+        CrosshairPart::Bottom => (Vec2::new(-half, gap), Vec2::new(thickness, length)),
+        CrosshairPart::Left => (Vec2::new(-(gap + length), -half), Vec2::new(length, thickness)),
+        CrosshairPart::Right => (Vec2::new(gap, -half), Vec2::new(length, thickness)),
+        CrosshairPart::Dot => (Vec2::splat(-half), Vec2::splat(thickness)),
+    }
+}
+
+pub fn update_crosshair(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    ui_scale: Res<UiScale>,
+    gun_query: Query<&GunController>,
+    view_query: Query<(&Camera, &Projection), With<PlayerView>>,
+    mut crosshair_query: Query<(&mut Crosshair, &mut Visibility)>,
+    mut piece_query: Query<
+        (&CrosshairPiece, &mut Style, &mut BackgroundColor, &mut Visibility),
+        Without<Crosshair>,
+    >,
+) {
+    let style = &settings.crosshair;
+    let gun_controller = gun_query.get_single().ok();
+
+    // the spread of the bullet that would go next, in pixels at the current fov
+    let mut target_spread = 0.;
+    if let (Some(gun_controller), Ok((camera, Projection::Perspective(perspective)))) =
+        (gun_controller, view_query.get_single())
+    {
+        let next_index = if gun_controller.time_since_last_shot >= gun_controller.recoil_reset_time
+        {
+            1
+        } else {
+            gun_controller.spray_index + 1
+        };
+        let spread = shot_spread(gun_controller, next_index);
+        let half_height = camera.logical_viewport_size().map_or(0., |size| size.y / 2.);
+        target_spread = spread.max_element() / f32::tan(perspective.fov / 2.) * half_height;
+    }
+    if !style.dynamic {
+        target_spread = 0.;
+    }
+
+    let hidden = !settings.crosshair_visible
+        || gun_controller.map_or(false, |gun_controller| gun_controller.aiming_down_sights);
+    let mut spread = 0.;
+    for (mut crosshair, mut visibility) in crosshair_query.iter_mut() {
+        let blend = f32::min(time.delta_seconds() * 20., 1.);
+        crosshair.spread += (target_spread - crosshair.spread) * blend;
+        spread = crosshair.spread;
+        *visibility = if hidden {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+    if hidden {
+        return;
+    }
+
+    let [r, g, b, a] = style.color;
+    let fill = Color::rgba_u8(r, g, b, a);
+    let outline = Color::rgba_u8(0, 0, 0, a);
+    let gap = style.gap + spread;
+    for (piece, mut piece_style, mut color, mut visibility) in piece_query.iter_mut() {
+        let shown = match piece.part {
+            CrosshairPart::Dot => style.dot,
+            _ => style.length > 0.,
+        } && (!piece.outline || style.outline > 0.);
+        *visibility = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        let (mut position, mut size) = piece_rect(piece.part, style, gap);
+        if piece.outline {
+            position -= Vec2::splat(style.outline);
+            size += Vec2::splat(style.outline * 2.);
+        }
+        // whole pixels keep thin lines sharp
+        let scale = ui_scale.scale as f32;
+        let position = position.round() / scale;
+        let size = size.round().max(Vec2::ONE) / scale;
+        piece_style.position.left = Val::Px(position.x);
+        piece_style.position.top = Val::Px(position.y);
+        piece_style.size = Size::new(Val::Px(size.x), Val::Px(size.y));
+        color.0 = if piece.outline { outline } else { fill };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_code_round_trips() {
+        let style = CrosshairStyle {
+            length: 4.5,
+            gap: -1.5,
+            thickness: 1.,
+            outline: 0.,
+            dot: true,
+            dynamic: false,
+            color: [255, 40, 200, 180],
+        };
+        let code = style.share_code();
+        assert_eq!(code.len(), 16);
+        assert_eq!(CrosshairStyle::from_share_code(&code), Some(style));
+        assert_eq!(
+            CrosshairStyle::from_share_code(&code.to_lowercase()),
+            Some(style)
+        );
+        assert_eq!(CrosshairStyle::from_share_code("not a code"), None);
+    }
+}
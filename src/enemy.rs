@@ -1,4 +1,6 @@
-use crate::{fps_movement::FPSMovement, AnimationEntityLink, EnemyAnimations};
+use crate::{
+    fps_movement::FPSMovement, kill_feed::KillEvent, AnimationEntityLink, EnemyAnimations,
+};
 use bevy::{prelude::*, render::view::NoFrustumCulling};
 use bevy_rapier3d::prelude::*;
 
@@ -10,6 +12,15 @@ pub struct Enemy {
     pub health : f32,
     pub respawn_timer : f32,
     pub respawned : bool,
+    // the last shot that hurt it, credited with the kill when it dies
+    pub last_hit: Option<EnemyHit>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnemyHit {
+    pub weapon: String,
+    pub headshot: bool,
+    pub distance: f32,
 }
 #[derive(Component)]
 pub struct HeadCollider {
@@ -29,14 +40,21 @@ pub fn rotate_to_player(
     mut animation_player_query: Query<&mut AnimationPlayer>,
     player_query: Query<(&Transform, &FPSMovement)>,
     mut commands: Commands,
-    mut enemy_query: Query<(&mut Enemy, Entity, &Children, &AnimationEntityLink)>,
+    mut kill_events: EventWriter<KillEvent>,
+    mut enemy_query: Query<(
+        &mut Enemy,
+        Entity,
+        &Children,
+        &AnimationEntityLink,
+        &GlobalTransform,
+    )>,
     get_child_query: Query<&Children>,
     name_query: Query<&Name>,
     _transform_query: Query<&mut Transform, Without<FPSMovement>>,
     time : Res<Time>,
 ) {
     if let Ok((_player_transform, _movement)) = player_query.get_single() {
-        for (mut enemy, entity, child, animation_entity) in enemy_query.iter_mut() {
+        for (mut enemy, entity, child, animation_entity, transform) in enemy_query.iter_mut() {
             enemy.respawn_timer-=time.delta_seconds();
             
             if let Ok(mut player) = animation_player_query.get_mut(animation_entity.0) {
@@ -45,6 +63,19 @@ pub fn rotate_to_player(
                     player.play(enemy_animations.0[1].clone_weak());
                     enemy.respawn_timer = 10.;
                     enemy.respawned = false;
+                    if let Some(hit) = enemy.last_hit.take() {
+                        let victim = name_query
+                            .get(entity)
+                            .map_or("ENEMY".to_string(), |name| name.as_str().to_string());
+                        kill_events.send(KillEvent {
+                            killer: "PLAYER".to_string(),
+                            victim,
+                            weapon: hit.weapon,
+                            headshot: hit.headshot,
+                            distance: hit.distance,
+                            position: transform.translation(),
+                        });
+                    }
                 }
                 else if enemy.respawned
                 {
@@ -1,49 +1,297 @@
 use bevy::{input::mouse::MouseMotion, prelude::*};
+use bevy_rapier3d::prelude::*;
 
-use crate::{lock_cursor::CursorLockState, vector_operations::move_towards};
+use crate::{
+    camera_effects::CameraEffects,
+    gun_control::GunController,
+    input_actions::{Action, InputActions},
+    lock_cursor::CursorLockState,
+    mouse_look::{accumulate_motion, take_look_delta},
+    recoil::RecoilOffset,
+    sensitivity::monitor_distance_ratio,
+    settings::Settings,
+};
 
 #[derive(Component)]
 pub struct FPSCamera {
     pub speed: f32,
+    // radians per mouse count
     pub sensitivity: f32,
+    pub vertical_sensitivity: f32,
+    pub ads_sensitivity_multiplier: f32,
+    pub aiming: bool,
     pub rotate_lock: f32,
+    // raw counts held back by mouse smoothing
+    pub pending_look: Vec2,
 
     pub rotation: Vec3,
-    pub recoil_shake: Vec3,
-    pub camera_shake_readjustment_factor: f32,
+    // radians per second the look turned last frame, x is pitch and y is yaw
+    pub look_speed: Vec2,
+    // recoil that moves the view, and the extra punch that only moves where bullets go
+    pub view_recoil: RecoilOffset,
+    pub aim_punch: RecoilOffset,
+    // -1 is fully leaned left and 1 fully right
+    pub lean: f32,
+    pub lean_angle: f32,
+    pub lean_distance: f32,
+    pub lean_speed: f32,
+}
+
+// the rendering camera, a child of the player entity
+#[derive(Component)]
+pub struct PlayerView {}
+
+// offset of the leaned view relative to the player's look rotation
+pub fn lean_transform(camera: &FPSCamera) -> Transform {
+    Transform::from_translation(Vec3::X * camera.lean * camera.lean_distance)
+        .with_rotation(Quat::from_rotation_z(-camera.lean * camera.lean_angle))
+}
+
+// where the view sits relative to the player, the lean plus the camera effects. the effects
+// only move the view, the player's rotation and so the aim and the collider stay put
+pub fn view_transform(camera: &FPSCamera, effects: Option<&CameraEffects>) -> Transform {
+    let offset = effects.map(|effects| effects.offset()).unwrap_or_default();
+    let rotation = Quat::from_rotation_y(offset.yaw)
+        * Quat::from_rotation_x(offset.pitch)
+        * Quat::from_rotation_z(offset.roll);
+    lean_transform(camera)
+        .mul_transform(Transform::from_translation(offset.translation).with_rotation(rotation))
 }
 
 pub fn move_camera(
     cursor_lock_state: Res<CursorLockState>,
+    actions: Res<InputActions>,
+    settings: Res<Settings>,
     mut motion_evr: EventReader<MouseMotion>,
     time: Res<Time>,
     mut camera_query: Query<(&mut Transform, &mut FPSCamera)>,
 ) {
-    if cursor_lock_state.state {
-        for (mut transform, mut camera) in camera_query.iter_mut() {
-            for ev in motion_evr.iter() {
-                camera.rotation.y -= ev.delta.x * camera.sensitivity;
-                camera.rotation.x -= ev.delta.y * camera.sensitivity;
-
-                camera.rotation.x =
-                    f32::clamp(camera.rotation.x, -camera.rotate_lock, camera.rotate_lock);
+    // every event is read each frame so motion from while the cursor was free never leaks in later
+    let mut raw_motion = accumulate_motion(motion_evr.iter().map(|ev| &ev.delta));
+    if !cursor_lock_state.state {
+        raw_motion = Vec2::ZERO;
+    }
+    let look_options = settings.mouse_look_options();
+    for (mut transform, mut camera) in camera_query.iter_mut() {
+        let multiplier = if camera.aiming {
+            camera.ads_sensitivity_multiplier
+        } else {
+            1.
+        };
+        let previous_look = Vec2::new(camera.rotation.x, camera.rotation.y);
+        let mut pending_look = camera.pending_look;
+        let look_delta = take_look_delta(
+            &mut pending_look,
+            raw_motion,
+            time.delta_seconds(),
+            &look_options,
+        );
+        camera.pending_look = pending_look;
+        camera.rotation.y -= look_delta.x * camera.sensitivity * multiplier;
+        camera.rotation.x -= look_delta.y * camera.vertical_sensitivity * multiplier;
+        camera.rotation.y -= actions.gamepad_look.x * multiplier;
+        camera.rotation.x += actions.gamepad_look.y * multiplier;
+        camera.rotation.x = f32::clamp(camera.rotation.x, -camera.rotate_lock, camera.rotate_lock);
+        camera.look_speed = if time.delta_seconds() > 0. {
+            (Vec2::new(camera.rotation.x, camera.rotation.y) - previous_look) / time.delta_seconds()
+        } else {
+            Vec2::ZERO
+        };
+
+        let x_quat = Quat::from_axis_angle(
+            Vec3::new(0., 1., 0.),
+            camera.rotation.y + camera.view_recoil.amount.y,
+        );
+
+        let y_quat = Quat::from_axis_angle(
+            Vec3::new(1., 0., 0.),
+            camera.rotation.x + camera.view_recoil.amount.x,
+        );
+
+        transform.rotation = x_quat * y_quat;
+    }
+}
+
+pub fn update_lean(
+    actions: Res<InputActions>,
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut camera_query: Query<
+        (&Transform, &mut FPSCamera, Option<&CameraEffects>),
+        Without<PlayerView>,
+    >,
+    mut view_query: Query<&mut Transform, With<PlayerView>>,
+) {
+    for (transform, mut camera, _) in camera_query.iter_mut() {
+        let mut target = 0.;
+        if actions.pressed(Action::LeanLeft) {
+            target -= 1.;
+        }
+        if actions.pressed(Action::LeanRight) {
+            target += 1.;
+        }
+        // stop the head short of any wall on the leaning side
+        let mut blocked = false;
+        if target != 0. {
+            let lean_direction = transform.right() * target;
+            let head_radius = 0.15;
+            if let Some((_, toi)) = rapier_context.cast_shape(
+                transform.translation,
+                Quat::IDENTITY,
+                lean_direction * camera.lean_distance,
+                &Collider::ball(head_radius),
+                1.,
+                QueryFilter::only_fixed().exclude_sensors(),
+            ) {
+                target *= toi.toi;
+                blocked = true;
+            }
+        }
+        let step = camera.lean_speed * time.delta_seconds();
+        camera.lean += (target - camera.lean).clamp(-step, step);
+        // a wall that appears mid-lean pushes the head back straight away
+        if blocked && target.abs() < camera.lean.abs() && target * camera.lean >= 0. {
+            camera.lean = target;
+        }
+    }
+    let Ok((_, camera, effects)) = camera_query.get_single() else {
+        return;
+    };
+    for mut transform in view_query.iter_mut() {
+        *transform = view_transform(camera, effects);
+    }
+}
+
+pub fn update_aim_fov(
+    settings: Res<Settings>,
+    time: Res<Time>,
+    gun_query: Query<&GunController>,
+    mut camera_query: Query<&mut FPSCamera>,
+    mut view_query: Query<&mut Projection, With<PlayerView>>,
+) {
+    let Ok(gun_controller) = gun_query.get_single() else {
+        return;
+    };
+    let hip_fov = settings.fov_radians();
+    let ads_fov = gun_controller.ads_fov.to_radians();
+    for mut camera in camera_query.iter_mut() {
+        camera.aiming = gun_controller.aiming_down_sights;
+        // matched against the full zoom so the multiplier doesn't drift during the transition
+        camera.ads_sensitivity_multiplier =
+            monitor_distance_ratio(hip_fov, ads_fov, settings.ads_monitor_distance)
+                * settings.ads_multiplier;
+
+        let target_fov = if camera.aiming { ads_fov } else { hip_fov };
+        for mut projection in view_query.iter_mut() {
+            if let Projection::Perspective(perspective) = projection.as_mut() {
+                let blend = f32::min(time.delta_seconds() * 15., 1.);
+                perspective.fov += (target_fov - perspective.fov) * blend;
             }
-            camera.recoil_shake = move_towards(
-                camera.recoil_shake,
-                Vec3::ZERO,
-                time.delta_seconds() * camera.camera_shake_readjustment_factor,
-            );
-            let x_quat = Quat::from_axis_angle(
-                Vec3::new(0., 1., 0.),
-                camera.rotation.y - camera.recoil_shake.x,
-            );
-
-            let y_quat = Quat::from_axis_angle(
-                Vec3::new(1., 0., 0.),
-                camera.rotation.x + camera.recoil_shake.y,
-            );
-
-            transform.rotation = x_quat * y_quat;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mouse_look::MouseLookOptions;
+
+    // a power of two so the expected rotations are exact in f32
+    const SENSITIVITY: f32 = 1. / 1024.;
+
+    fn look_app(locked: bool) -> App {
+        let mut app = App::new();
+        app.add_event::<MouseMotion>()
+            .insert_resource(CursorLockState {
+                state: locked,
+                allow_lock: true,
+            })
+            .insert_resource(InputActions::default())
+            .insert_resource(Settings::default())
+            .insert_resource(Time::default())
+            .add_system(move_camera);
+        app.world.spawn((
+            Transform::default(),
+            FPSCamera {
+                speed: 300.,
+                sensitivity: SENSITIVITY,
+                vertical_sensitivity: SENSITIVITY,
+                ads_sensitivity_multiplier: 1.,
+                aiming: false,
+                rotate_lock: 88_f32.to_radians(),
+                pending_look: Vec2::ZERO,
+                rotation: Vec3::ZERO,
+                look_speed: Vec2::ZERO,
+                view_recoil: RecoilOffset::default(),
+                aim_punch: RecoilOffset::default(),
+                lean: 0.,
+                lean_angle: 0.2,
+                lean_distance: 0.45,
+                lean_speed: 5.,
+            },
+        ));
+        app
+    }
+
+    fn send_motion(app: &mut App, delta: Vec2, count: usize) {
+        for _ in 0..count {
+            app.world.send_event(MouseMotion { delta });
+        }
+    }
+
+    fn rotation(app: &mut App) -> Vec3 {
+        app.world.query::<&FPSCamera>().single(&app.world).rotation
+    }
+
+    #[test]
+    fn every_event_in_a_frame_is_applied() {
+        let mut app = look_app(true);
+        send_motion(&mut app, Vec2::new(0.5, -0.25), 1000);
+        app.update();
+        let rotation = rotation(&mut app);
+        assert_eq!(rotation.y, -500. * SENSITIVITY);
+        assert_eq!(rotation.x, 250. * SENSITIVITY);
+    }
+
+    #[test]
+    fn frame_boundaries_do_not_change_the_result() {
+        let mut app = look_app(true);
+        send_motion(&mut app, Vec2::new(3., 1.), 7);
+        app.update();
+        send_motion(&mut app, Vec2::new(3., 1.), 1);
+        app.update();
+        app.update();
+        send_motion(&mut app, Vec2::new(3., 1.), 24);
+        app.update();
+        let rotation = rotation(&mut app);
+        assert_eq!(rotation.y, -96. * SENSITIVITY);
+        assert_eq!(rotation.x, -32. * SENSITIVITY);
+    }
+
+    #[test]
+    fn motion_while_unlocked_is_discarded() {
+        let mut app = look_app(false);
+        send_motion(&mut app, Vec2::new(40., 40.), 10);
+        app.update();
+        app.world.resource_mut::<CursorLockState>().state = true;
+        app.update();
+        assert_eq!(rotation(&mut app), Vec3::ZERO);
+    }
+
+    #[test]
+    fn smoothing_delays_but_keeps_all_motion() {
+        let options = MouseLookOptions {
+            smoothing: 0.02,
+            acceleration: 0.,
+            acceleration_cap: 2.,
+        };
+        let mut pending = Vec2::ZERO;
+        let mut applied =
+            take_look_delta(&mut pending, Vec2::new(300., -120.), 1. / 144., &options);
+        assert!(applied.x < 300.);
+        for _ in 0..500 {
+            applied += take_look_delta(&mut pending, Vec2::ZERO, 1. / 144., &options);
+        }
+        assert!((applied - Vec2::new(300., -120.)).length() < 0.001);
+    }
+}
@@ -1,103 +1,755 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
-use crate::{fps_camera::FPSCamera, MapStatus};
+use crate::{
+    fps_camera::FPSCamera,
+    input_actions::{Action, InputActions},
+    level::Ladder,
+    MapStatus,
+};
+
+pub const GRAVITY: f32 = 9.81;
+
 #[derive(Component)]
 pub struct FPSMovement {
     pub acceleration: f32,
 
     pub speed: f32,
+
+    pub velocity: Vec3,
+    pub grounded: bool,
+    pub ground_friction: f32,
+    pub jump_speed: f32,
+
+    pub model: MovementModel,
+
+    pub state: MovementState,
+    pub sprint_multiplier: f32,
+    pub crouch_multiplier: f32,
+    // horizontal speed cap, kept from the last grounded frame while in the air
+    pub max_speed: f32,
+    pub slide_boost: f32,
+    pub slide_friction: f32,
+    pub slide_time: f32,
+    pub slide_timer: f32,
+
+    pub collider_radius: f32,
+    pub stand_half_height: f32,
+    pub crouch_half_height: f32,
+    pub collider_half_height: f32,
+    // meters per second the collider grows or shrinks when crouching
+    pub crouch_speed: f32,
+
+    pub stamina_settings: StaminaSettings,
+    pub stamina: f32,
+    pub stamina_regen_timer: f32,
+    // set when stamina runs out, sprinting stays off until it refills past recover_fraction
+    pub exhausted: bool,
+
+    pub fall_damage: FallDamage,
+
+    pub climb_speed: f32,
+    // after jumping off a ladder it can't be grabbed again until this runs out
+    pub ladder_detach_timer: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FallDamage {
+    // landing slower than this in meters per second is harmless
+    pub min_speed: f32,
+    pub damage_per_speed: f32,
+}
+
+impl Default for FallDamage {
+    fn default() -> Self {
+        FallDamage {
+            min_speed: 10.,
+            damage_per_speed: 12.,
+        }
+    }
+}
+
+impl FallDamage {
+    pub fn none() -> Self {
+        FallDamage {
+            damage_per_speed: 0.,
+            ..default()
+        }
+    }
+    pub fn damage(&self, impact_speed: f32) -> f32 {
+        f32::max(impact_speed - self.min_speed, 0.) * self.damage_per_speed
+    }
+}
+
+pub struct PlayerLanded {
+    pub impact_speed: f32,
+    pub damage: f32,
+}
+
+// landings slower than this are steps and small hops, not worth a camera dip or a sound
+pub const MIN_LANDING_SPEED: f32 = 2.5;
+
+// looking further down than this makes forward climb down a ladder instead of up
+pub const LADDER_DESCEND_PITCH: f32 = 0.35;
+pub const LADDER_DETACH_TIME: f32 = 0.4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StaminaSettings {
+    pub max: f32,
+    // per second
+    pub sprint_cost: f32,
+    pub jump_cost: f32,
+    // per second
+    pub regen_rate: f32,
+    pub regen_delay: f32,
+    pub recover_fraction: f32,
+}
+
+impl Default for StaminaSettings {
+    fn default() -> Self {
+        StaminaSettings {
+            max: 100.,
+            sprint_cost: 20.,
+            jump_cost: 15.,
+            regen_rate: 25.,
+            regen_delay: 1.,
+            recover_fraction: 0.3,
+        }
+    }
+}
+
+impl StaminaSettings {
+    pub fn unlimited() -> Self {
+        StaminaSettings {
+            sprint_cost: 0.,
+            jump_cost: 0.,
+            ..default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MovementState {
+    Walking,
+    Sprinting,
+    Crouching,
+    Sliding,
+    Airborne,
+    Climbing,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MovementModel {
+    Classic,
+    Source(SourceMovement),
+}
+
+// quake / source style movement, speeds are in meters per second
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SourceMovement {
+    // sv_accelerate
+    pub ground_accelerate: f32,
+    // sv_airaccelerate
+    pub air_accelerate: f32,
+    // sv_friction
+    pub friction: f32,
+    // sv_stopspeed, friction never slows by less than this speed would
+    pub stop_speed: f32,
+    // the 30 unit cap on air wish speed that makes strafing gain speed
+    pub air_wish_speed: f32,
+    // hard cap on horizontal speed
+    pub max_speed: f32,
+    // holding jump hops again on the frame the player lands
+    pub auto_hop: bool,
+}
+
+impl Default for SourceMovement {
+    fn default() -> Self {
+        SourceMovement {
+            ground_accelerate: 10.,
+            air_accelerate: 100.,
+            friction: 5.2,
+            stop_speed: 1.5,
+            air_wish_speed: 0.6,
+            max_speed: 20.,
+            auto_hop: true,
+        }
+    }
+}
+
+pub fn player_controller(
+    collider_half_height: f32,
+    collider_radius: f32,
+) -> KinematicCharacterController {
+    KinematicCharacterController {
+        // the camera pitches the player entity, so the shape is counter-rotated to stay upright
+        custom_shape: Some((
+            Collider::capsule_y(collider_half_height, collider_radius),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+        )),
+        offset: CharacterLength::Absolute(0.01),
+        max_slope_climb_angle: 45_f32.to_radians(),
+        min_slope_slide_angle: 30_f32.to_radians(),
+        autostep: Some(CharacterAutostep {
+            max_height: CharacterLength::Absolute(0.4),
+            min_width: CharacterLength::Absolute(0.15),
+            include_dynamic_bodies: false,
+        }),
+        snap_to_ground: Some(CharacterLength::Absolute(0.3)),
+        apply_impulse_to_dynamic_bodies: false,
+        ..default()
+    }
 }
 
 pub fn player_movement(
     map_status : Res<MapStatus>,
     time: Res<Time>,
     rapier_context: Res<RapierContext>,
+    ladder_query: Query<(), With<Ladder>>,
     mut movement_query: Query<(
-        &Transform,
-        &mut Damping,
-        &mut FPSCamera,
+        &FPSCamera,
+        &mut Transform,
+        &mut Collider,
         &mut FPSMovement,
-        &mut Velocity,
+        &mut KinematicCharacterController,
+        Option<&KinematicCharacterControllerOutput>,
     )>,
-    key: Res<Input<KeyCode>>,
+    actions: Res<InputActions>,
+    mut landed_events: EventWriter<PlayerLanded>,
 ) {
-    for (transform, mut damping, camera, movement, mut velocity) in movement_query.iter_mut() {
+    let delta_seconds = time.delta_seconds();
+    if delta_seconds <= 0. {
+        return;
+    }
+    for (camera, mut transform, mut collider, mut movement, mut controller, output) in
+        movement_query.iter_mut()
+    {
+        if let Some(output) = output {
+            let was_grounded = movement.grounded;
+            movement.grounded = output.grounded;
+            // velocity.y still holds the speed the player was falling at when the ground was hit
+            let impact_speed = -movement.velocity.y;
+            if !was_grounded
+                && movement.grounded
+                && movement.state != MovementState::Climbing
+                && impact_speed >= MIN_LANDING_SPEED
+            {
+                landed_events.send(PlayerLanded {
+                    impact_speed,
+                    damage: movement.fall_damage.damage(impact_speed),
+                });
+            }
+            // take back whatever walls and ceilings refused last frame, so pushing into a wall
+            // doesn't build up velocity that makes the character stutter along it
+            let blocked = output.desired_translation - output.effective_translation;
+            let blocked_horizontal = Vec2::new(blocked.x, blocked.z);
+            if blocked_horizontal.length() > 0.001 {
+                let desired_horizontal =
+                    Vec2::new(output.desired_translation.x, output.desired_translation.z);
+                let effective_horizontal =
+                    Vec2::new(output.effective_translation.x, output.effective_translation.z);
+                if desired_horizontal.length() > 0. {
+                    let kept = effective_horizontal.length() / desired_horizontal.length();
+                    let horizontal = effective_horizontal.normalize_or_zero()
+                        * Vec2::new(movement.velocity.x, movement.velocity.z).length()
+                        * kept.min(1.);
+                    movement.velocity.x = horizontal.x;
+                    movement.velocity.z = horizontal.y;
+                }
+            }
+            if movement.velocity.y > 0. && blocked.y > 0.001 {
+                movement.velocity.y = 0.;
+            }
+        }
+
         let mut direction = Vec2::new(0., 0.);
+        let forward = actions.move_axis.y;
+        let right = actions.move_axis.x;
+        direction.y += -f32::cos(camera.rotation.y) * forward;
+        direction.x += -f32::sin(camera.rotation.y) * forward;
+        direction.y += f32::cos(camera.rotation.y + f32::to_radians(90.)) * right;
+        direction.x += f32::sin(camera.rotation.y + f32::to_radians(90.)) * right;
 
-        let mut air_modifier = 1.0;
+        // keep partial stick deflection, but never move faster diagonally
+        if direction.length() > 1. {
+            direction = direction.normalize();
+        }
 
-        let mut grounded = false;
-        let ray = Ray {
-            origin: transform.translation - 0.5,
-            direction: Vec3::new(0., -1., 0.),
-        };
+        let horizontal_speed = Vec2::new(movement.velocity.x, movement.velocity.z).length();
+        movement.ladder_detach_timer -= delta_seconds;
+        let on_ladder = movement.ladder_detach_timer <= 0.
+            && touching_ladder(&rapier_context, &ladder_query, transform.translation, &movement);
+        let previous_state = movement.state;
+        movement.state = next_movement_state(&movement, &actions, horizontal_speed, on_ladder);
+        if movement.state == MovementState::Sliding && previous_state != MovementState::Sliding {
+            movement.slide_timer = movement.slide_time;
+            let slide_boost = movement.slide_boost;
+            movement.velocity.x *= slide_boost;
+            movement.velocity.z *= slide_boost;
+        }
 
-        // Then cast the ray.
-        let hit = rapier_context.cast_ray(
-            ray.origin,
-            ray.direction,
-            1.,
-            true,
-            QueryFilter::only_fixed(),
+        let wants_crouch =
+            actions.pressed(Action::Crouch) || movement.state == MovementState::Sliding;
+        update_crouch_height(
+            &mut movement,
+            &mut transform,
+            &mut collider,
+            &mut controller,
+            &rapier_context,
+            wants_crouch,
+            delta_seconds,
         );
-
-        if let Some((_entity, _toi)) = hit {
-            grounded = true;
+        // a ceiling can keep the player crouched after the key is released
+        let stuck_crouched = movement.collider_half_height < movement.stand_half_height;
+        if stuck_crouched
+            && matches!(
+                movement.state,
+                MovementState::Walking | MovementState::Sprinting
+            )
+        {
+            movement.state = MovementState::Crouching;
         }
 
-        if grounded == false {
-            damping.linear_damping = 0.;
-            air_modifier = 0.05;
-        } else {
-            damping.linear_damping = 8.;
-        }
-        if key.pressed(KeyCode::W) {
-            direction.y += -f32::cos(camera.rotation.y);
-            direction.x += -f32::sin(camera.rotation.y);
-        } else if key.pressed(KeyCode::S) {
-            direction.y += f32::cos(camera.rotation.y);
-            direction.x += f32::sin(camera.rotation.y);
-        }
-        if key.pressed(KeyCode::D) {
-            direction.y += f32::cos(camera.rotation.y + f32::to_radians(90.));
-            direction.x += f32::sin(camera.rotation.y + f32::to_radians(90.));
-        } else if key.pressed(KeyCode::A) {
-            direction.y += f32::cos(camera.rotation.y - f32::to_radians(90.));
-            direction.x += f32::sin(camera.rotation.y - f32::to_radians(90.));
-        }
-
-        if key.just_pressed(KeyCode::Space) {
-            if grounded {
-                velocity.linvel.y = 4.;
+        match movement.state {
+            MovementState::Walking => movement.max_speed = movement.speed,
+            MovementState::Sprinting => {
+                movement.max_speed = movement.speed * movement.sprint_multiplier
+            }
+            MovementState::Crouching => {
+                movement.max_speed = movement.speed * movement.crouch_multiplier
             }
+            MovementState::Sliding => movement.max_speed = horizontal_speed,
+            MovementState::Airborne | MovementState::Climbing => {}
         }
 
-        if direction.length() != 0. {
-            direction = direction.normalize();
+        if movement.state == MovementState::Climbing {
+            if actions.just_pressed(Action::Jump) {
+                detach_from_ladder(&mut movement, camera.rotation.y);
+            } else {
+                ladder_movement(&mut movement, camera.rotation.x, actions.move_axis.y, direction);
+            }
+            regenerate_stamina(&mut movement, delta_seconds);
+            controller.translation = Some(movement.velocity * delta_seconds);
+            continue;
         }
 
-        velocity.linvel.x +=
-            direction.x * movement.acceleration * time.delta_seconds() * air_modifier;
-        velocity.linvel.z +=
-            direction.y * movement.acceleration * time.delta_seconds() * air_modifier;
+        let jump_input = match movement.model {
+            MovementModel::Source(source) if source.auto_hop => actions.pressed(Action::Jump),
+            _ => actions.just_pressed(Action::Jump),
+        };
+        let jumped = jump_input
+            && movement.grounded
+            && movement.stamina >= movement.stamina_settings.jump_cost;
+        if jumped {
+            movement.velocity.y = movement.jump_speed;
+            movement.grounded = false;
+            let jump_cost = movement.stamina_settings.jump_cost;
+            spend_stamina(&mut movement, jump_cost);
+        }
+        if movement.state == MovementState::Sprinting {
+            let sprint_cost = movement.stamina_settings.sprint_cost * delta_seconds;
+            spend_stamina(&mut movement, sprint_cost);
+        }
+        regenerate_stamina(&mut movement, delta_seconds);
 
-        let net_velocity = Vec2::new(velocity.linvel.x, velocity.linvel.z).length();
-        let multiplier;
-        if net_velocity > movement.speed {
-            multiplier = movement.speed / net_velocity;
+        if movement.grounded == false {
+            movement.velocity.y -= GRAVITY * delta_seconds;
         } else {
-            multiplier = 1.0;
+            movement.velocity.y = movement.velocity.y.max(0.);
         }
 
-        velocity.linvel.x *= multiplier;
-        velocity.linvel.z *= multiplier;
+        if movement.state == MovementState::Sliding {
+            slide_movement(&mut movement, delta_seconds);
+        } else {
+            match movement.model {
+                MovementModel::Classic => {
+                    classic_movement(&mut movement, direction, delta_seconds)
+                }
+                MovementModel::Source(source) => {
+                    source_movement(&mut movement, &source, direction, delta_seconds)
+                }
+            }
+        }
 
         if map_status.loaded == false
         {
-            velocity.linvel.y = 0.;
+            movement.velocity.y = 0.;
         }
-        //velocity.linvel.x = f32::clamp(velocity.linvel.x,-movement.speed,movement.speed);
-        //velocity.linvel.z = f32::clamp(velocity.linvel.z,-movement.speed,movement.speed);
+
+        controller.translation = Some(movement.velocity * delta_seconds);
+    }
+}
+
+// the camera pitches the player entity, so the capsule is turned back the other way to stand
+// straight in the world
+pub fn upright_capsule(half_height: f32, radius: f32, rotation: Quat) -> Collider {
+    let up = rotation.inverse() * Vec3::Y * half_height;
+    Collider::capsule(-up, up, radius)
+}
+
+pub fn keep_controller_upright(
+    mut controller_query: Query<
+        (
+            &Transform,
+            &FPSMovement,
+            &mut Collider,
+            &mut KinematicCharacterController,
+        ),
+        Changed<Transform>,
+    >,
+) {
+    for (transform, movement, mut collider, mut controller) in controller_query.iter_mut() {
+        if let Some((_, _, rotation)) = controller.custom_shape.as_mut() {
+            *rotation = transform.rotation.inverse();
+        }
+        // contacts and scene queries see the body collider, not the controller's shape
+        *collider = upright_capsule(
+            movement.collider_half_height,
+            movement.collider_radius,
+            transform.rotation,
+        );
+    }
+}
+
+fn classic_movement(movement: &mut FPSMovement, direction: Vec2, delta_seconds: f32) {
+    let mut air_modifier = 1.0;
+    if movement.grounded == false {
+        air_modifier = 0.05;
+    } else {
+        let friction = 1. / (1. + delta_seconds * movement.ground_friction);
+        movement.velocity.x *= friction;
+        movement.velocity.z *= friction;
+    }
+
+    let acceleration = movement.acceleration;
+    movement.velocity.x += direction.x * acceleration * delta_seconds * air_modifier;
+    movement.velocity.z += direction.y * acceleration * delta_seconds * air_modifier;
+
+    let net_velocity = Vec2::new(movement.velocity.x, movement.velocity.z).length();
+    let multiplier;
+    if net_velocity > movement.max_speed {
+        multiplier = movement.max_speed / net_velocity;
+    } else {
+        multiplier = 1.0;
+    }
+
+    movement.velocity.x *= multiplier;
+    movement.velocity.z *= multiplier;
+}
+
+fn source_movement(
+    movement: &mut FPSMovement,
+    source: &SourceMovement,
+    direction: Vec2,
+    delta_seconds: f32,
+) {
+    let wish_speed = direction.length() * movement.max_speed;
+    let wish_dir = Vec3::new(direction.x, 0., direction.y).normalize_or_zero();
+    let mut horizontal = Vec3::new(movement.velocity.x, 0., movement.velocity.z);
+
+    // a jump this frame already cleared grounded, so a perfectly timed hop skips friction
+    if movement.grounded {
+        horizontal = apply_friction(horizontal, source.friction, source.stop_speed, delta_seconds);
+        horizontal = accelerate(
+            horizontal,
+            wish_dir,
+            wish_speed,
+            wish_speed,
+            source.ground_accelerate,
+            delta_seconds,
+        );
+    } else {
+        horizontal = accelerate(
+            horizontal,
+            wish_dir,
+            wish_speed.min(source.air_wish_speed),
+            wish_speed,
+            source.air_accelerate,
+            delta_seconds,
+        );
+    }
+
+    if horizontal.length() > source.max_speed {
+        horizontal = horizontal.normalize() * source.max_speed;
+    }
+    movement.velocity.x = horizontal.x;
+    movement.velocity.z = horizontal.z;
+}
+
+fn slide_movement(movement: &mut FPSMovement, delta_seconds: f32) {
+    let friction = 1. / (1. + delta_seconds * movement.slide_friction);
+    movement.velocity.x *= friction;
+    movement.velocity.z *= friction;
+    movement.slide_timer -= delta_seconds;
+}
+
+fn spend_stamina(movement: &mut FPSMovement, amount: f32) {
+    if amount <= 0. {
+        return;
+    }
+    movement.stamina = f32::max(movement.stamina - amount, 0.);
+    movement.stamina_regen_timer = movement.stamina_settings.regen_delay;
+    if movement.stamina == 0. {
+        movement.exhausted = true;
+    }
+}
+
+fn regenerate_stamina(movement: &mut FPSMovement, delta_seconds: f32) {
+    let settings = movement.stamina_settings;
+    if movement.stamina_regen_timer > 0. {
+        movement.stamina_regen_timer -= delta_seconds;
+        return;
+    }
+    movement.stamina =
+        f32::min(movement.stamina + settings.regen_rate * delta_seconds, settings.max);
+    if movement.stamina >= settings.max * settings.recover_fraction {
+        movement.exhausted = false;
+    }
+}
+
+pub fn reset_stamina(movement: &mut FPSMovement, settings: StaminaSettings) {
+    movement.stamina_settings = settings;
+    movement.stamina = settings.max;
+    movement.stamina_regen_timer = 0.;
+    movement.exhausted = false;
+}
+
+fn touching_ladder(
+    rapier_context: &RapierContext,
+    ladder_query: &Query<(), With<Ladder>>,
+    position: Vec3,
+    movement: &FPSMovement,
+) -> bool {
+    let mut touching = false;
+    rapier_context.intersections_with_shape(
+        position,
+        Quat::IDENTITY,
+        &Collider::capsule_y(movement.collider_half_height, movement.collider_radius + 0.05),
+        QueryFilter::new(),
+        |entity| {
+            touching = ladder_query.contains(entity);
+            !touching
+        },
+    );
+    touching
+}
+
+// like source ladders, forward climbs towards where the player is looking
+fn ladder_movement(movement: &mut FPSMovement, pitch: f32, forward: f32, direction: Vec2) {
+    let climb_direction = if pitch < -LADDER_DESCEND_PITCH { -1. } else { 1. };
+    movement.velocity.y = forward * climb_direction * movement.climb_speed;
+    let horizontal = direction * movement.climb_speed * 0.5;
+    movement.velocity.x = horizontal.x;
+    movement.velocity.z = horizontal.y;
+}
+
+fn detach_from_ladder(movement: &mut FPSMovement, yaw: f32) {
+    let backwards = Vec2::new(f32::sin(yaw), f32::cos(yaw)) * movement.climb_speed;
+    movement.velocity = Vec3::new(backwards.x, movement.jump_speed * 0.5, backwards.y);
+    movement.ladder_detach_timer = LADDER_DETACH_TIME;
+    movement.state = MovementState::Airborne;
+    movement.grounded = false;
+}
+
+fn next_movement_state(
+    movement: &FPSMovement,
+    actions: &InputActions,
+    horizontal_speed: f32,
+    on_ladder: bool,
+) -> MovementState {
+    if on_ladder {
+        return MovementState::Climbing;
+    }
+    if !movement.grounded {
+        return MovementState::Airborne;
+    }
+    let crouch_held = actions.pressed(Action::Crouch);
+    let sprint_held = actions.pressed(Action::Sprint)
+        && actions.move_axis.y > 0.5
+        && !actions.pressed(Action::Aim)
+        && !movement.exhausted;
+    match movement.state {
+        MovementState::Sliding
+            if crouch_held
+                && movement.slide_timer > 0.
+                && horizontal_speed > movement.speed * movement.crouch_multiplier =>
+        {
+            MovementState::Sliding
+        }
+        MovementState::Sprinting
+            if actions.just_pressed(Action::Crouch) && horizontal_speed > movement.speed =>
+        {
+            MovementState::Sliding
+        }
+        _ if crouch_held => MovementState::Crouching,
+        _ if sprint_held => MovementState::Sprinting,
+        _ => MovementState::Walking,
+    }
+}
+
+// on the ground the head comes down and the feet stay put, in the air the feet tuck up instead,
+// which is what lets a crouch jump clear higher ledges
+fn update_crouch_height(
+    movement: &mut FPSMovement,
+    transform: &mut Transform,
+    collider: &mut Collider,
+    controller: &mut KinematicCharacterController,
+    rapier_context: &RapierContext,
+    wants_crouch: bool,
+    delta_seconds: f32,
+) {
+    let half_height = movement.collider_half_height;
+    let target = if wants_crouch {
+        movement.crouch_half_height
+    } else {
+        movement.stand_half_height
+    };
+    let step = movement.crouch_speed * delta_seconds;
+    let new_half_height = if target < half_height {
+        f32::max(half_height - step, target)
+    } else if target > half_height {
+        let grown = f32::min(half_height + step, target);
+        let center_offset = if movement.grounded {
+            grown - half_height
+        } else {
+            0.
+        };
+        // lifted slightly so the floor under the player doesn't count as a ceiling
+        let blocked = rapier_context
+            .intersection_with_shape(
+                transform.translation + Vec3::Y * (center_offset + 0.02),
+                Quat::IDENTITY,
+                &Collider::capsule_y(grown, movement.collider_radius),
+                QueryFilter::only_fixed().exclude_sensors(),
+            )
+            .is_some();
+        if blocked {
+            half_height
+        } else {
+            grown
+        }
+    } else {
+        half_height
+    };
+    if new_half_height == half_height {
+        return;
+    }
+    if movement.grounded {
+        transform.translation.y += new_half_height - half_height;
+    }
+    movement.collider_half_height = new_half_height;
+    if let Some((custom_shape, _, _)) = controller.custom_shape.as_mut() {
+        *custom_shape = Collider::capsule_y(new_half_height, movement.collider_radius);
+    }
+    *collider = upright_capsule(new_half_height, movement.collider_radius, transform.rotation);
+}
+
+// how hard footsteps bob the view or the gun, 1 at full running speed and 0 off the ground,
+// eased so it doesn't snap on landing or stopping
+pub fn ease_bob_intensity(
+    intensity: f32,
+    movement: &FPSMovement,
+    scale: f32,
+    delta_seconds: f32,
+) -> f32 {
+    let bobbing = movement.grounded
+        && !matches!(
+            movement.state,
+            MovementState::Sliding | MovementState::Climbing
+        );
+    let target = if bobbing {
+        (horizontal_speed(movement) / movement.speed.max(0.01)).min(1.5) * scale
+    } else {
+        0.
+    };
+    intensity + (target - intensity) * f32::min(delta_seconds * 10., 1.)
+}
+
+pub fn horizontal_speed(movement: &FPSMovement) -> f32 {
+    Vec2::new(movement.velocity.x, movement.velocity.z).length()
+}
+
+pub fn apply_friction(velocity: Vec3, friction: f32, stop_speed: f32, delta_seconds: f32) -> Vec3 {
+    let speed = velocity.length();
+    if speed < 0.001 {
+        return Vec3::ZERO;
+    }
+    let drop = speed.max(stop_speed) * friction * delta_seconds;
+    velocity * (f32::max(speed - drop, 0.) / speed)
+}
+
+// only the speed along wish_dir is capped, which is what lets air strafing build speed
+pub fn accelerate(
+    velocity: Vec3,
+    wish_dir: Vec3,
+    capped_wish_speed: f32,
+    wish_speed: f32,
+    accelerate: f32,
+    delta_seconds: f32,
+) -> Vec3 {
+    let current_speed = velocity.dot(wish_dir);
+    let add_speed = capped_wish_speed - current_speed;
+    if add_speed <= 0. {
+        return velocity;
+    }
+    let accel_speed = f32::min(accelerate * wish_speed * delta_seconds, add_speed);
+    velocity + wish_dir * accel_speed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn friction_stops_slow_movement_at_stop_speed() {
+        // below stop_speed the drop is worked out as if moving at stop_speed
+        let velocity = apply_friction(Vec3::new(1., 0., 0.), 5., 1.5, 0.1);
+        assert!((velocity.x - 0.25).abs() < 1e-6);
+        // friction brings the player to a stop but never pushes them backwards
+        assert_eq!(apply_friction(Vec3::new(1., 0., 0.), 5., 1.5, 1.), Vec3::ZERO);
+        let velocity = apply_friction(Vec3::new(0., 0., -10.), 5., 1.5, 0.01);
+        assert!((velocity.z + 9.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn ground_acceleration_stops_at_wish_speed() {
+        let mut velocity = Vec3::ZERO;
+        for _ in 0..100 {
+            velocity = accelerate(velocity, Vec3::X, 6., 6., 10., 0.01);
+        }
+        assert!((velocity.x - 6.).abs() < 1e-5);
+        // already faster than wished, so nothing is added or taken away
+        let fast = Vec3::new(9., 0., 0.);
+        assert_eq!(accelerate(fast, Vec3::X, 6., 6., 10., 0.01), fast);
+    }
+
+    #[test]
+    fn air_strafing_gains_speed() {
+        let source = SourceMovement::default();
+        let velocity = Vec3::new(0., 0., -6.);
+        // wishing sideways while moving forward only caps the sideways part
+        let velocity = accelerate(
+            velocity,
+            Vec3::X,
+            source.air_wish_speed,
+            6.,
+            source.air_accelerate,
+            0.01,
+        );
+        assert!((velocity.x - source.air_wish_speed).abs() < 1e-6);
+        assert!(velocity.length() > 6.);
+    }
+
+    #[test]
+    fn fall_damage_starts_at_min_speed() {
+        let fall_damage = FallDamage::default();
+        assert_eq!(fall_damage.damage(fall_damage.min_speed - 1.), 0.);
+        assert_eq!(fall_damage.damage(fall_damage.min_speed), 0.);
+        assert_eq!(
+            fall_damage.damage(fall_damage.min_speed + 2.),
+            2. * fall_damage.damage_per_speed
+        );
+        assert_eq!(FallDamage::none().damage(50.), 0.);
     }
 }
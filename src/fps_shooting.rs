@@ -6,21 +6,34 @@ use bevy_rapier3d::prelude::*;
 use rand::Rng;
 
 use crate::bullet_tracer::BulletTracer;
-use crate::enemy::{HeadCollider, BodyCollider, LegCollider, Enemy};
-use crate::fps_camera::FPSCamera;
-use crate::gun_control::{translate_gun_position, GunController};
+use crate::enemy::{HeadCollider, BodyCollider, LegCollider, Enemy, EnemyHit};
+use crate::fps_camera::{FPSCamera, PlayerView};
+use crate::game_mode::MatchStats;
+use crate::gun_control::{FireMode, GunController};
+use crate::input_actions::{Action, InputActions};
+use crate::random::{GameRng, RandomStream, StreamRng};
+use crate::recoil::{aim_angles, aim_rotation, apply_recoil_kick};
 use crate::rotation_operations::quaternion_look_rotation;
-use crate::score_ui::ScoreText;
-use crate::vector_operations::move_towards;
+use crate::third_person::{weapon_hit, ThirdPersonCamera};
 use crate::{AnimationEntityLink, Animations};
 
+// sent for every bullet. aim is the pitch and yaw it left at before spread, origin and
+// direction are the camera ray it actually took
+pub struct ShotFired {
+    pub aim: Vec2,
+    pub spray_index: usize,
+    pub origin: Vec3,
+    pub direction: Vec3,
+    // what was hit, where and the surface normal there
+    pub hit: Option<(Entity, Vec3, Vec3)>,
+}
+
 #[derive(Component)]
 pub struct ShootableTarget {
     pub health: f32,
     pub max_health: f32,
 }
 pub fn play_gun_animations(
-    _buttons: Res<Input<MouseButton>>,
     mut player_query: Query<&mut AnimationPlayer>,
     animations: Res<Animations>,
     mut gun_query: Query<
@@ -50,7 +63,8 @@ pub fn update_bullet_params(
         (Without<FPSCamera>, Without<ShootableTarget>),
     >,
     time: Res<Time>,
-    buttons: Res<Input<MouseButton>>,
+    actions: Res<InputActions>,
+    mut match_stats: ResMut<MatchStats>,
 ) {
     for (mut gun_controller, _gun_transform, _animation_entity) in gun_query.iter_mut() {
         if gun_controller.time_since_last_shot >= gun_controller.recoil_reset_time {
@@ -59,22 +73,30 @@ pub fn update_bullet_params(
         gun_controller.timer -= time.delta_seconds();
         gun_controller.time_since_last_shot += time.delta_seconds();
         gun_controller.reloading_timer -= time.delta_seconds();
+        gun_controller.aiming_down_sights =
+            actions.pressed(Action::Aim) && !gun_controller.lowered;
+        if actions.just_pressed(Action::FireMode) {
+            gun_controller.fire_mode = gun_controller.fire_mode.next();
+        }
+        let trigger = match gun_controller.fire_mode {
+            FireMode::Auto => actions.pressed(Action::Fire),
+            FireMode::Semi => actions.just_pressed(Action::Fire),
+        };
         if gun_controller.reloading_timer < 0. {
             gun_controller.shoot = false;
-            if buttons.pressed(MouseButton::Left) {
-                
-                if gun_controller.timer <= 0. {
-                    gun_controller.shoot = true;
-                    gun_controller.bullets -= 1;
-                    gun_controller.spray_index += 1;
-                    if gun_controller.bullets <= 0 {
-                        gun_controller.bullets = gun_controller.magazine_size;
-                        gun_controller.reloading_timer = gun_controller.reloading_time;
-                        gun_controller.spray_index = 0;
-                    }
-                    gun_controller.timer = gun_controller.cooldown;
-                    
+            let reloaded = actions.just_pressed(Action::Reload) && gun_controller.reload();
+            let ready = gun_controller.timer <= 0. && gun_controller.bullets > 0;
+            if !reloaded && trigger && !gun_controller.lowered && ready {
+                gun_controller.shoot = true;
+                gun_controller.bullets -= 1;
+                if !match_stats.finished {
+                    match_stats.shots_fired += 1;
+                }
+                gun_controller.spray_index += 1;
+                if gun_controller.bullets == 0 {
+                    gun_controller.reload();
                 }
+                gun_controller.timer = gun_controller.cooldown;
             }
         }
     }
@@ -84,14 +106,9 @@ pub fn update_shots(
         (&mut GunController, &mut Transform, &AnimationEntityLink),
         (Without<FPSCamera>, Without<ShootableTarget>),
     >,
-    _score_query: Query<&mut ScoreText, With<Text>>,
-    mut camera_query: Query<(
-        &Camera,
-        &GlobalTransform,
-        &mut Transform,
-        Entity,
-        &mut FPSCamera,
-    )>,
+    mut shot_events: EventWriter<ShotFired>,
+    mut camera_query: Query<(Entity, &mut FPSCamera, Option<&ThirdPersonCamera>)>,
+    view_query: Query<(&Camera, &GlobalTransform), With<PlayerView>>,
     mut enemy_query : Query<&mut Enemy>,
     mut head_query: Query<&mut HeadCollider>,
     mut body_query: Query<&mut BodyCollider>,
@@ -100,93 +117,37 @@ pub fn update_shots(
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    time: Res<Time>,
     windows: Query<&Window, With<PrimaryWindow>>,
     rapier_context: Res<RapierContext>,
+    mut match_stats: ResMut<MatchStats>,
+    mut rng: ResMut<GameRng>,
 ) {
-    for (mut gun_controller, mut gun_transform, _animation_entity) in gun_query.iter_mut() {
+    for (mut gun_controller, gun_transform, _animation_entity) in gun_query.iter_mut() {
         if gun_controller.reloading_timer < 0. {
             if gun_controller.shoot == true {
 
                 let window = windows.single();
 
-                for (
-                    camera,
-                    camera_transform,
-                    camera_transform_non_global,
-                    entity,
-                    mut fps_camera,
-                ) in camera_query.iter_mut()
-                {
+                let Ok((camera, camera_transform)) = view_query.get_single() else {
+                    return;
+                };
+                for (entity, mut fps_camera, third_person) in camera_query.iter_mut() {
                     gun_controller.time_since_last_shot = 0.;
                     let Some(ray) = camera.viewport_to_world(camera_transform, Vec2::new(window.width()/2.,window.height()/2.)) else { return; };
 
-                    let x_quat =
-                        Quat::from_axis_angle(Vec3::new(0., 1., 0.), fps_camera.rotation.y);
-
-                    let y_quat =
-                        Quat::from_axis_angle(Vec3::new(1., 0., 0.), fps_camera.rotation.x);
-
-                    let mut camera_transform_non_corrupted = Transform::from_xyz(
-                        camera_transform_non_global.translation.x,
-                        camera_transform_non_global.translation.y,
-                        camera_transform_non_global.translation.z,
+                    // the pattern kicks before the shot, so the first bullet goes where it's aimed
+                    apply_recoil_kick(
+                        &mut gun_controller,
+                        &mut fps_camera,
+                        rng.stream(RandomStream::GunKick).gen_range(-1.0..1.0),
                     );
-
-                    camera_transform_non_corrupted.rotation = x_quat * y_quat;
-
-                    let mut rng = rand::thread_rng();
-
-                    let ray_direction;
-                    let spray_rand_movement_added =
-                        gun_controller.spray_rand + gun_controller.movement_inaccuracy;
-                    if gun_controller.spray_index > 4 {
-                        ray_direction = (camera_transform_non_corrupted.forward()
-                            + (camera_transform_non_corrupted.up()
-                                * (rng.gen_range(
-                                    -spray_rand_movement_added..spray_rand_movement_added,
-                                ) + gun_controller.spray_pattern[gun_controller.spray_index-1]
-                                    .y))
-                            + (camera_transform_non_corrupted.right()
-                                * (rng.gen_range(
-                                    -spray_rand_movement_added..spray_rand_movement_added,
-                                ) + gun_controller.spray_pattern[gun_controller.spray_index-1]
-                                    .x)))
-                            .normalize();
-                    }
-                    else if gun_controller.spray_index == 1
-                    {
-                        ray_direction = (camera_transform_non_corrupted.forward()
-                            + (camera_transform_non_corrupted.up()
-                                * (rng.gen_range(
-                                    -gun_controller.spray_rand / 200.0
-                                        ..gun_controller.spray_rand /200.0,
-                                ) + gun_controller.spray_pattern[gun_controller.spray_index-1]
-                                    .y))
-                            + (camera_transform_non_corrupted.right()
-                                * (rng.gen_range(
-                                    -spray_rand_movement_added / 24.0
-                                        ..spray_rand_movement_added / 24.0,
-                                ) + gun_controller.spray_pattern[gun_controller.spray_index-1]
-                                    .x)))
-                            .normalize();
-                    } 
-                    else {
-                        ray_direction = (camera_transform_non_corrupted.forward()
-                            + (camera_transform_non_corrupted.up()
-                                * (rng.gen_range(
-                                    -gun_controller.spray_rand / 3.0
-                                        ..gun_controller.spray_rand / 3.0,
-                                ) + gun_controller.spray_pattern[gun_controller.spray_index-1]
-                                    .y))
-                            + (camera_transform_non_corrupted.right()
-                                * (rng.gen_range(
-                                    -spray_rand_movement_added / 3.0
-                                        ..spray_rand_movement_added / 3.0,
-                                ) + gun_controller.spray_pattern[gun_controller.spray_index-1]
-                                    .x)))
-                            .normalize();
-                    }
+                    let aim = aim_rotation(&fps_camera);
+                    let spread = shot_spread(&gun_controller, gun_controller.spray_index);
+                    let spread_rng = rng.stream(RandomStream::Spread);
+                    let ray_direction = (aim * Vec3::NEG_Z
+                        + aim * Vec3::Y * random_spread(spread_rng, spread.y)
+                        + aim * Vec3::X * random_spread(spread_rng, spread.x))
+                        .normalize();
 
                     let hit = rapier_context.cast_ray_and_get_normal(
                         ray.origin,
@@ -194,48 +155,33 @@ pub fn update_shots(
                         //ray.direction,
                         f32::MAX,
                         true,
-                        QueryFilter::new().exclude_collider(entity),
-                    );
-                    fps_camera.recoil_shake =
-                        (ray_direction - camera_transform_non_corrupted.forward()) * 0.7;
-                    gun_controller.recoil_shake =
-                        (ray_direction - camera_transform_non_corrupted.forward()) * 1.;
-
-                    let mut placebo_camera = Transform::from_xyz(
-                        camera_transform_non_global.translation.x,
-                        camera_transform_non_global.translation.y,
-                        camera_transform_non_global.translation.z,
-                    );
-
-                    gun_controller.recoil_shake = move_towards(
-                        gun_controller.recoil_shake,
-                        Vec3::ZERO,
-                        time.delta_seconds() * gun_controller.smooth_scale,
-                    );
-
-                    let x_quat = Quat::from_axis_angle(
-                        Vec3::new(0., 1., 0.),
-                        fps_camera.rotation.y - gun_controller.recoil_shake.x,
-                    );
-
-                    let y_quat = Quat::from_axis_angle(
-                        Vec3::new(1., 0., 0.),
-                        fps_camera.rotation.x + gun_controller.recoil_shake.y,
-                    );
-
-                    placebo_camera.rotation = x_quat * y_quat;
-
-                    gun_transform.translation = translate_gun_position(&placebo_camera);
-
-                    gun_transform.look_at(
-                        camera_transform_non_global.translation + placebo_camera.forward() * 100.,
-                        Vec3::Y,
-                    );
-                    gun_transform.scale = Vec3::new(
-                        gun_controller.gun_scale,
-                        gun_controller.gun_scale,
-                        gun_controller.gun_scale,
+                        QueryFilter::new().exclude_collider(entity).exclude_sensors(),
                     );
+                    // in third person the shot leaves the character's weapon instead of the camera
+                    let muzzle = third_person.and_then(|third_person| third_person.muzzle);
+                    let hit = match muzzle {
+                        Some(muzzle) => {
+                            let aim_point = hit
+                                .map(|(_, intersection)| intersection.point)
+                                .unwrap_or(ray.origin + ray_direction * 1000.);
+                            weapon_hit(
+                                &rapier_context,
+                                muzzle,
+                                aim_point,
+                                QueryFilter::new().exclude_collider(entity).exclude_sensors(),
+                            )
+                        }
+                        None => hit,
+                    };
+                    shot_events.send(ShotFired {
+                        aim: aim_angles(&fps_camera),
+                        spray_index: gun_controller.spray_index,
+                        origin: ray.origin,
+                        direction: ray_direction,
+                        hit: hit.map(|(entity, intersection)| {
+                            (entity, intersection.point, intersection.normal)
+                        }),
+                    });
 
                     if let Some((entity, ray_intersection)) = hit {
                         let bullet_tracer_material = materials.add(StandardMaterial {
@@ -252,22 +198,29 @@ pub fn update_shots(
                             NotShadowCaster {},
                             BulletTracer {
                                 direction: Vec3::new(0., 0., 0.),
-                                start_position: gun_transform.translation
+                                start_position: muzzle.unwrap_or(
+                                gun_transform.translation
                                     + (gun_transform.up() * 0.56704 * gun_controller.gun_scale)
                                     + (gun_transform.forward()
                                         * 3.13735
                                         * gun_controller.gun_scale),
+                            ),
 
                                 end_position: ray_intersection.point,
                                 life_time: 0.3,
                             },
                         ));
+                        let enemy_hit = |headshot| EnemyHit {
+                            weapon: gun_controller.definition.clone(),
+                            headshot,
+                            distance: ray_intersection.point.distance(ray.origin),
+                        };
                         let mut spawn_bullet_hole = true;
                         if let Ok(head) = head_query.get_mut(entity)
                         {
                             if let Ok(mut enemy) = enemy_query.get_mut(head.enemy_reference)
                             {
-                                enemy.health-= 100.;
+                                damage_enemy(&mut enemy, 100., enemy_hit(true), &mut match_stats);
                             }
                             spawn_bullet_hole = false;
                         }
@@ -275,7 +228,7 @@ pub fn update_shots(
                         {
                             if let Ok(mut enemy) = enemy_query.get_mut(leg.enemy_reference)
                             {
-                                enemy.health-= 10.;
+                                damage_enemy(&mut enemy, 10., enemy_hit(false), &mut match_stats);
                             }
                             spawn_bullet_hole = false;
                         }
@@ -283,7 +236,7 @@ pub fn update_shots(
                         {
                             if let Ok(mut enemy) = enemy_query.get_mut(body.enemy_reference)
                             {
-                                enemy.health-= 20.;
+                                damage_enemy(&mut enemy, 20., enemy_hit(false), &mut match_stats);
                             }
                             spawn_bullet_hole = false;
                         }
@@ -336,7 +289,48 @@ pub fn update_shots(
     }
 }
 
-pub fn generate_target_position(rng: &mut rand::rngs::ThreadRng) -> Vec3 {
+// how far a shot can stray, x to the right and y up. the first shot is nearly exact and the
+// spray gets wilder the longer it goes
+pub fn shot_spread(gun_controller: &GunController, spray_index: usize) -> Vec2 {
+    let spray_rand = gun_controller.spray_rand;
+    let with_movement = spray_rand + gun_controller.movement_inaccuracy;
+    if spray_index > 4 {
+        Vec2::new(with_movement, with_movement)
+    } else if spray_index == 1 {
+        Vec2::new(with_movement / 24., spray_rand / 200.)
+    } else {
+        Vec2::new(with_movement / 3., spray_rand / 3.)
+    }
+}
+
+pub fn random_spread(rng: &mut StreamRng, range: f32) -> f32 {
+    if range > 0. {
+        rng.gen_range(-range..range)
+    } else {
+        0.
+    }
+}
+
+pub fn damage_enemy(enemy: &mut Enemy, damage: f32, hit: EnemyHit, match_stats: &mut MatchStats) {
+    if enemy.health <= 0. {
+        return;
+    }
+    enemy.health -= damage;
+    let headshot = hit.headshot;
+    enemy.last_hit = Some(hit);
+    if match_stats.finished {
+        return;
+    }
+    match_stats.shots_hit += 1;
+    if enemy.health <= 0. {
+        match_stats.kills += 1;
+        if headshot {
+            match_stats.headshots += 1;
+        }
+    }
+}
+
+pub fn generate_target_position(rng: &mut StreamRng) -> Vec3 {
     return Vec3::new(
         rng.gen_range(-5..5) as f32 * 0.4,
         rng.gen_range(1..11) as f32 * 0.4,
@@ -345,6 +339,7 @@ pub fn generate_target_position(rng: &mut rand::rngs::ThreadRng) -> Vec3 {
 }
 pub fn update_targets(
     _commands: Commands,
+    mut rng: ResMut<GameRng>,
     mut target_query: Query<(&mut ShootableTarget, &mut Transform)>,
 ) {
     let mut pos_vec = Vec::new();
@@ -355,9 +350,9 @@ pub fn update_targets(
     for (mut target, mut transform) in target_query.iter_mut() {
         let original_position;
         if target.health <= 0. {
-            let mut rng: rand::rngs::ThreadRng = rand::thread_rng();
+            let rng = rng.stream(RandomStream::Targets);
             original_position = pos_vec[i];
-            pos_vec[i] = generate_target_position(&mut rng);
+            pos_vec[i] = generate_target_position(rng);
             transform.translation = pos_vec[i];
             target.health = target.max_health;
 
@@ -366,7 +361,7 @@ pub fn update_targets(
                 for j in 0..pos_vec.len() {
                     unique = true;
                     if (pos_vec[i] == pos_vec[j] || pos_vec[i] == original_position) && i != j {
-                        pos_vec[i] = generate_target_position(&mut rng);
+                        pos_vec[i] = generate_target_position(rng);
                         transform.translation = pos_vec[i];
                         unique = false;
                         break;
@@ -377,3 +372,24 @@ pub fn update_targets(
         i += 1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn spread_replays_and_stays_in_range() {
+        let spray = |seed: u64| -> Vec<f32> {
+            let mut rng = StreamRng::seed_from_u64(seed);
+            (0..1000).map(|_| random_spread(&mut rng, 0.05)).collect()
+        };
+        let samples = spray(1);
+        assert_eq!(samples, spray(1));
+        assert!(samples.iter().all(|sample| sample.abs() <= 0.05));
+        // uniform spread should average out close to dead center
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        assert!(mean.abs() < 0.005, "mean spread {}", mean);
+        assert_eq!(random_spread(&mut StreamRng::seed_from_u64(1), 0.), 0.);
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use crate::{
+    fps_movement::{FallDamage, MovementModel, SourceMovement, StaminaSettings},
+    game_state::AppState,
+    random::match_seed,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    AimTrainer,
+    Waves,
+    Movement,
+}
+
+impl GameMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::AimTrainer => "aim_trainer",
+            GameMode::Waves => "waves",
+            GameMode::Movement => "movement",
+        }
+    }
+    pub fn movement_model(&self) -> MovementModel {
+        match self {
+            GameMode::AimTrainer | GameMode::Waves => MovementModel::Classic,
+            GameMode::Movement => MovementModel::Source(SourceMovement::default()),
+        }
+    }
+    pub fn stamina_settings(&self) -> StaminaSettings {
+        match self {
+            GameMode::AimTrainer => StaminaSettings::default(),
+            GameMode::Waves => StaminaSettings {
+                regen_rate: 18.,
+                ..default()
+            },
+            GameMode::Movement => StaminaSettings::unlimited(),
+        }
+    }
+    // whether every run of the mode plays out the same
+    pub fn fixed_seed(&self) -> bool {
+        matches!(self, GameMode::AimTrainer)
+    }
+    pub fn starting_armor(&self) -> f32 {
+        match self {
+            GameMode::Waves => 50.,
+            GameMode::AimTrainer | GameMode::Movement => 0.,
+        }
+    }
+    // none means reloading never runs dry
+    pub fn reserve_ammo(&self) -> Option<usize> {
+        match self {
+            // five magazines, so a wave is won by hitting rather than holding the trigger
+            GameMode::Waves => Some(125),
+            GameMode::AimTrainer | GameMode::Movement => None,
+        }
+    }
+    pub fn fall_damage(&self) -> FallDamage {
+        match self {
+            GameMode::AimTrainer | GameMode::Waves => FallDamage::default(),
+            // movement maps are built around long drops
+            GameMode::Movement => FallDamage::none(),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct MatchStats {
+    pub mode: GameMode,
+    pub scenario: String,
+    pub map: String,
+    pub duration: f32,
+    pub elapsed: f32,
+    pub finished: bool,
+    pub seed: u64,
+
+    pub kills: u32,
+    pub headshots: u32,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+}
+
+impl MatchStats {
+    pub fn new(mode: GameMode, scenario: &str, map: &str, duration: f32) -> Self {
+        MatchStats {
+            mode,
+            scenario: scenario.to_string(),
+            map: map.to_string(),
+            duration,
+            elapsed: 0.,
+            finished: false,
+            seed: match_seed(mode.fixed_seed(), &format!("{}/{}", mode.name(), scenario)),
+            kills: 0,
+            headshots: 0,
+            shots_fired: 0,
+            shots_hit: 0,
+        }
+    }
+    pub fn score(&self) -> i32 {
+        (self.kills * 100 + self.headshots * 25) as i32
+    }
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            return 0.;
+        }
+        self.shots_hit as f32 / self.shots_fired as f32
+    }
+    pub fn time_remaining(&self) -> f32 {
+        f32::max(self.duration - self.elapsed, 0.)
+    }
+}
+
+pub fn update_match_timer(
+    time: Res<Time>,
+    mut match_stats: ResMut<MatchStats>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if match_stats.finished {
+        return;
+    }
+    match_stats.elapsed += time.delta_seconds();
+    if match_stats.elapsed >= match_stats.duration {
+        match_stats.finished = true;
+        next_state.set(AppState::PostMatch);
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    controls_menu::ControlsMenu,
+    enemy::Enemy,
+    fps_camera::FPSCamera,
+    fps_movement::{reset_stamina, FPSMovement},
+    game_mode::MatchStats,
+    gun_control::GunController,
+    input_actions::{Action, InputActions},
+    lock_cursor::{set_cursor_grab, CursorLockState},
+    player_health::PlayerHealth,
+    random::GameRng,
+    settings::SettingsMenu,
+    spectator::Spectator,
+};
+
+#[derive(States, Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum AppState {
+    #[default]
+    Loading,
+    LoadingFailed,
+    MainMenu,
+    InGame,
+    Paused,
+    PostMatch,
+}
+
+pub const PLAYER_SPAWN: Vec3 = Vec3::new(0.0, 1.0, 4.0);
+
+pub fn despawn_screen<T: Component>(mut commands: Commands, screen_query: Query<Entity, With<T>>) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn toggle_pause(
+    actions: Res<InputActions>,
+    settings_menu: Res<SettingsMenu>,
+    controls_menu: Res<ControlsMenu>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if settings_menu.open || controls_menu.open {
+        return;
+    }
+    if actions.just_pressed(Action::Pause) {
+        match state.0 {
+            AppState::InGame => next_state.set(AppState::Paused),
+            AppState::Paused => next_state.set(AppState::InGame),
+            _ => {}
+        }
+    }
+}
+
+pub fn pause_world(mut time: ResMut<Time>, mut rapier_config: ResMut<RapierConfiguration>) {
+    time.pause();
+    rapier_config.physics_pipeline_active = false;
+}
+
+pub fn resume_world(mut time: ResMut<Time>, mut rapier_config: ResMut<RapierConfiguration>) {
+    time.unpause();
+    rapier_config.physics_pipeline_active = true;
+}
+
+pub fn release_cursor(
+    mut cursor_lock_state: ResMut<CursorLockState>,
+    mut primary_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    cursor_lock_state.state = false;
+    if let Ok(mut primary) = primary_query.get_single_mut() {
+        set_cursor_grab(&mut primary, false);
+    }
+}
+
+pub fn is_new_match(match_stats: Res<MatchStats>) -> bool {
+    match_stats.elapsed == 0.
+}
+
+pub fn reset_match(
+    match_stats: Res<MatchStats>,
+    mut spectator: ResMut<Spectator>,
+    mut rng: ResMut<GameRng>,
+    mut player_query: Query<(
+        &mut Transform,
+        &mut FPSMovement,
+        &mut FPSCamera,
+        &mut PlayerHealth,
+    )>,
+    mut gun_query: Query<&mut GunController>,
+    mut enemy_query: Query<&mut Enemy>,
+) {
+    spectator.active = false;
+    rng.reseed(match_stats.seed);
+    for (mut transform, mut movement, mut camera, mut player_health) in player_query.iter_mut() {
+        transform.translation = PLAYER_SPAWN;
+        movement.velocity = Vec3::ZERO;
+        movement.model = match_stats.mode.movement_model();
+        reset_stamina(&mut movement, match_stats.mode.stamina_settings());
+        movement.fall_damage = match_stats.mode.fall_damage();
+        player_health.health = player_health.max_health;
+        player_health.max_armor = match_stats.mode.starting_armor();
+        player_health.armor = player_health.max_armor;
+        camera.rotation = Vec3::ZERO;
+        camera.view_recoil.reset();
+        camera.aim_punch.reset();
+    }
+    for mut gun_controller in gun_query.iter_mut() {
+        gun_controller.bullets = gun_controller.magazine_size;
+        gun_controller.reserve_ammo = match_stats.mode.reserve_ammo();
+        gun_controller.reloading_timer = 0.;
+        gun_controller.spray_index = 0;
+        gun_controller.gun_kick.reset();
+    }
+    for mut enemy in enemy_query.iter_mut() {
+        enemy.health = 100.;
+        enemy.respawned = true;
+        enemy.respawn_timer = 0.;
+    }
+}
@@ -1,33 +1,87 @@
 use bevy::prelude::*;
-use bevy_rapier3d::prelude::Velocity;
 
-use crate::{fps_camera::FPSCamera, vector_operations::move_towards};
+use crate::{
+    camera_effects::CameraEffects,
+    fps_camera::{view_transform, FPSCamera},
+    fps_movement::{FPSMovement, MovementState},
+    recoil::{RecoilOffset, RecoilSettings},
+    weapon_sway::{WeaponSway, WeaponSwaySettings},
+};
 #[derive(Component)]
 pub struct AmmoText {}
 #[derive(Component)]
 pub struct GunController {
+    // name of the file in assets/weapons the gun was loaded from
+    pub definition: String,
     pub magazine_size: usize,
     pub timer: f32,
     pub cooldown: f32,
     pub offset: Vec3,
     pub gun_scale: f32,
     pub shoot: bool,
-    pub dynamic_offset: Vec3,
-    pub target_offset: Vec3,
+    pub sway_settings: WeaponSwaySettings,
+    pub sway: WeaponSway,
     pub spray_pattern: Vec<Vec2>,
     pub spray_index: usize,
     pub recoil_reset_time: f32,
     pub time_since_last_shot: f32,
-    pub smooth_scale: f32,
     pub current_camera_transform: Transform,
-    pub recoil_shake: Vec3,
+    pub recoil: RecoilSettings,
+    // the gun model's own kick, it never changes where bullets go
+    pub gun_kick: RecoilOffset,
     pub aiming_down_sights: bool,
+    pub ads_fov: f32,
     pub spray_rand: f32,
     pub reloading_timer: f32,
     pub reloading_time: f32,
     pub bullets: usize,
+    // rounds left to reload from, none for a bottomless supply
+    pub reserve_ammo: Option<usize>,
+    pub fire_mode: FireMode,
     pub movement_inaccuracy: f32,
+    // lowered while sprinting, the gun can't fire or aim
+    pub lowered: bool,
 }
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FireMode {
+    Auto,
+    // one shot per trigger pull
+    Semi,
+}
+
+impl FireMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FireMode::Auto => "AUTO",
+            FireMode::Semi => "SEMI",
+        }
+    }
+    pub fn next(&self) -> Self {
+        match self {
+            FireMode::Auto => FireMode::Semi,
+            FireMode::Semi => FireMode::Auto,
+        }
+    }
+}
+
+impl GunController {
+    // fills the magazine from the reserve, false when there was nothing to load
+    pub fn reload(&mut self) -> bool {
+        let missing = self.magazine_size.saturating_sub(self.bullets);
+        let loaded = self.reserve_ammo.map_or(missing, |reserve| reserve.min(missing));
+        if loaded == 0 {
+            return false;
+        }
+        self.bullets += loaded;
+        if let Some(reserve) = self.reserve_ammo.as_mut() {
+            *reserve -= loaded;
+        }
+        self.reloading_timer = self.reloading_time;
+        self.spray_index = 0;
+        true
+    }
+}
+
 pub fn translate_gun_position(camera_transform: &Transform) -> Vec3 {
     let mut position = camera_transform.translation;
     position += camera_transform.forward() * 0.35;
@@ -37,11 +91,20 @@ pub fn translate_gun_position(camera_transform: &Transform) -> Vec3 {
 }
 pub fn apply_movement_inaccuracy(
     mut gun_query: Query<&mut GunController, Without<FPSCamera>>,
-    movement_query: Query<&Velocity, With<FPSCamera>>,
+    movement_query: Query<&FPSMovement, With<FPSCamera>>,
 ) {
-    for velocity in movement_query.iter() {
+    for movement in movement_query.iter() {
         if let Ok(mut gun_controller) = gun_query.get_single_mut() {
-            gun_controller.movement_inaccuracy = velocity.linvel.length() / 9.;
+            let speed_inaccuracy = movement.velocity.length() / 9.;
+            gun_controller.movement_inaccuracy = match movement.state {
+                MovementState::Crouching => speed_inaccuracy * 0.5,
+                MovementState::Walking => speed_inaccuracy,
+                MovementState::Sliding => speed_inaccuracy * 1.5 + 0.03,
+                MovementState::Sprinting => speed_inaccuracy * 2. + 0.05,
+                MovementState::Airborne => speed_inaccuracy * 2. + 0.08,
+                MovementState::Climbing => speed_inaccuracy * 2. + 0.05,
+            };
+            gun_controller.lowered = movement.state == MovementState::Sprinting;
         }
     }
 }
@@ -58,42 +121,56 @@ pub fn update_ammo_count_text(
         }
     }
 }
-pub fn update_gun_control(
-    time: Res<Time>,
-    mut gun_query: Query<(&mut Transform, &mut GunController), Without<FPSCamera>>,
-    mut camera_query: Query<(&mut Transform, &FPSCamera), Without<GunController>>,
-) {
-    if let Ok((camera_transform, camera)) = camera_query.get_single_mut() {
-        if let Ok((mut transform, mut gun_controller)) = gun_query.get_single_mut() {
-            let mut placebo_camera = Transform::from_xyz(
-                camera_transform.translation.x,
-                camera_transform.translation.y,
-                camera_transform.translation.z,
-            );
+// where the player's eye is this frame, the view camera's own offsets included so the gun
+// stays on the crosshair through effects and leaning
+fn placebo_camera(
+    camera_transform: &Transform,
+    camera: &FPSCamera,
+    effects: Option<&CameraEffects>,
+) -> Transform {
+    let mut placebo_camera = Transform::from_xyz(
+        camera_transform.translation.x,
+        camera_transform.translation.y,
+        camera_transform.translation.z,
+    );
 
-            gun_controller.recoil_shake = move_towards(
-                gun_controller.recoil_shake,
-                Vec3::ZERO,
-                time.delta_seconds() * gun_controller.smooth_scale,
-            );
+    let x_quat = Quat::from_axis_angle(
+        Vec3::new(0., 1., 0.),
+        camera.rotation.y + camera.view_recoil.amount.y,
+    );
 
-            let x_quat = Quat::from_axis_angle(
-                Vec3::new(0., 1., 0.),
-                camera.rotation.y - gun_controller.recoil_shake.x,
-            );
+    let y_quat = Quat::from_axis_angle(
+        Vec3::new(1., 0., 0.),
+        camera.rotation.x + camera.view_recoil.amount.x,
+    );
 
-            let y_quat = Quat::from_axis_angle(
-                Vec3::new(1., 0., 0.),
-                camera.rotation.x + gun_controller.recoil_shake.y,
-            );
+    placebo_camera.rotation = x_quat * y_quat;
+    placebo_camera.mul_transform(view_transform(camera, effects))
+}
 
-            placebo_camera.rotation = x_quat * y_quat;
-            transform.translation = translate_gun_position(&placebo_camera);
+pub fn update_gun_control(
+    mut gun_query: Query<(&mut Transform, &GunController), Without<FPSCamera>>,
+    mut camera_query: Query<
+        (&mut Transform, &FPSCamera, Option<&CameraEffects>),
+        Without<GunController>,
+    >,
+) {
+    if let Ok((camera_transform, camera, effects)) = camera_query.get_single_mut() {
+        if let Ok((mut transform, gun_controller)) = gun_query.get_single_mut() {
+            let placebo_camera = placebo_camera(&camera_transform, camera, effects);
+            let sway = gun_controller.sway;
+            let gun_kick = gun_controller.gun_kick.amount;
+            transform.translation = translate_gun_position(&placebo_camera)
+                + placebo_camera.rotation * sway.translation_offset
+                + placebo_camera.back() * gun_kick.y;
 
             transform.look_at(
-                camera_transform.translation + placebo_camera.forward() * 100.,
-                Vec3::Y,
+                placebo_camera.translation + placebo_camera.forward() * 100.,
+                placebo_camera.up(),
             );
+            transform.rotate_local_x(sway.rotation_offset.x + gun_kick.x);
+            transform.rotate_local_y(sway.rotation_offset.y);
+            transform.rotate_local_z(sway.rotation_offset.z + gun_kick.z);
             transform.scale = Vec3::new(
                 gun_controller.gun_scale,
                 gun_controller.gun_scale,
@@ -102,3 +179,44 @@ pub fn update_gun_control(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gun_points_where_the_view_camera_does_with_effects() {
+        let camera = FPSCamera {
+            speed: 300.,
+            sensitivity: 0.001,
+            vertical_sensitivity: 0.001,
+            ads_sensitivity_multiplier: 1.,
+            aiming: false,
+            rotate_lock: 88_f32.to_radians(),
+            pending_look: Vec2::ZERO,
+            rotation: Vec3::new(-0.3, 1.1, 0.),
+            look_speed: Vec2::ZERO,
+            view_recoil: RecoilOffset::default(),
+            aim_punch: RecoilOffset::default(),
+            lean: 0.5,
+            lean_angle: 0.2,
+            lean_distance: 0.45,
+            lean_speed: 5.,
+        };
+        let mut effects = CameraEffects::default();
+        effects.head_bob.phase = 1.;
+        effects.head_bob.intensity = 1.;
+        effects.sway.roll = 0.03;
+        effects.landing_dip.dip = 0.1;
+        effects.add_trauma(0.8);
+        effects.trauma.time = 0.37;
+        // the player entity as move_camera leaves it, with the view camera as its child
+        let camera_transform = Transform::from_xyz(3., 1.8, -2.).with_rotation(
+            Quat::from_rotation_y(camera.rotation.y) * Quat::from_rotation_x(camera.rotation.x),
+        );
+        let view = camera_transform.mul_transform(view_transform(&camera, Some(&effects)));
+        let gun = placebo_camera(&camera_transform, &camera, Some(&effects));
+        assert!(gun.forward().abs_diff_eq(view.forward(), 1e-5));
+        assert!(gun.up().abs_diff_eq(view.up(), 1e-5));
+    }
+}
@@ -0,0 +1,389 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    enemy::Enemy,
+    fps_camera::FPSCamera,
+    game_mode::MatchStats,
+    gun_control::{AmmoText, GunController},
+    player_health::{HealthText, PlayerHealth},
+    score_ui::ScoreText,
+    settings::Settings,
+    stamina_ui::spawn_stamina_bar,
+};
+
+// the hud is laid out for this window height and scaled to the real one
+pub const HUD_REFERENCE_HEIGHT: f32 = 1080.;
+const MINIMAP_SIZE: f32 = 140.;
+// meters from the player to the edge of the minimap
+const MINIMAP_RANGE: f32 = 25.;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HudAnchor {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    CenterLeft,
+    CenterRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+pub const ALL_ANCHORS: [HudAnchor; 8] = [
+    HudAnchor::TopLeft,
+    HudAnchor::TopCenter,
+    HudAnchor::TopRight,
+    HudAnchor::CenterLeft,
+    HudAnchor::CenterRight,
+    HudAnchor::BottomLeft,
+    HudAnchor::BottomCenter,
+    HudAnchor::BottomRight,
+];
+
+impl HudAnchor {
+    // where widgets stack vertically and horizontally within the safe area
+    fn alignment(&self) -> (JustifyContent, AlignItems) {
+        let vertical = match self {
+            HudAnchor::TopLeft | HudAnchor::TopCenter | HudAnchor::TopRight => {
+                JustifyContent::FlexStart
+            }
+            HudAnchor::CenterLeft | HudAnchor::CenterRight => JustifyContent::Center,
+            _ => JustifyContent::FlexEnd,
+        };
+        let horizontal = match self {
+            HudAnchor::TopLeft | HudAnchor::CenterLeft | HudAnchor::BottomLeft => {
+                AlignItems::FlexStart
+            }
+            HudAnchor::TopCenter | HudAnchor::BottomCenter => AlignItems::Center,
+            _ => AlignItems::FlexEnd,
+        };
+        (vertical, horizontal)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HudWidgetKind {
+    Health,
+    Armor,
+    Stamina,
+    Ammo,
+    Reserve,
+    FireMode,
+    WeaponIcon,
+    Score,
+    Timer,
+    KillFeed,
+    Minimap,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct HudWidgetLayout {
+    pub kind: HudWidgetKind,
+    pub anchor: HudAnchor,
+    pub visible: bool,
+}
+
+// widgets sharing an anchor stack in this order, top to bottom
+pub fn default_hud_layout() -> Vec<HudWidgetLayout> {
+    let widget = |kind, anchor| HudWidgetLayout {
+        kind,
+        anchor,
+        visible: true,
+    };
+    vec![
+        widget(HudWidgetKind::Minimap, HudAnchor::TopLeft),
+        widget(HudWidgetKind::Timer, HudAnchor::TopCenter),
+        widget(HudWidgetKind::Score, HudAnchor::TopCenter),
+        widget(HudWidgetKind::KillFeed, HudAnchor::TopRight),
+        widget(HudWidgetKind::Armor, HudAnchor::BottomLeft),
+        widget(HudWidgetKind::Health, HudAnchor::BottomLeft),
+        widget(HudWidgetKind::Stamina, HudAnchor::BottomCenter),
+        widget(HudWidgetKind::WeaponIcon, HudAnchor::BottomRight),
+        widget(HudWidgetKind::FireMode, HudAnchor::BottomRight),
+        widget(HudWidgetKind::Ammo, HudAnchor::BottomRight),
+        widget(HudWidgetKind::Reserve, HudAnchor::BottomRight),
+    ]
+}
+
+#[derive(Component)]
+pub struct HudRoot {}
+
+#[derive(Component)]
+pub struct HudWidget {
+    pub kind: HudWidgetKind,
+}
+
+// text widgets that are filled in here rather than by the module that owns the value
+#[derive(Component)]
+pub struct HudText(pub HudWidgetKind);
+
+#[derive(Component)]
+pub struct MinimapDot {}
+
+// ui scale follows the window height so the hud covers the same share of the screen at any
+// resolution, on top of the player's own scale
+pub fn update_ui_scale(
+    settings: Res<Settings>,
+    mut ui_scale: ResMut<UiScale>,
+    primary_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Ok(primary) = primary_query.get_single() else {
+        return;
+    };
+    let scale = (settings.ui_scale * primary.height() / HUD_REFERENCE_HEIGHT) as f64;
+    if scale > 0. && ui_scale.scale != scale {
+        ui_scale.scale = scale;
+    }
+}
+
+fn hud_text(asset_server: &AssetServer, value: &str, font_size: f32) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font: asset_server.load("font.ttf"),
+            font_size,
+            color: Color::WHITE,
+        },
+    )
+    .with_style(Style {
+        margin: UiRect::all(Val::Px(4.0)),
+        ..default()
+    })
+}
+
+fn spawn_widget(parent: &mut ChildBuilder, asset_server: &AssetServer, kind: HudWidgetKind) {
+    let widget = HudWidget { kind };
+    match kind {
+        HudWidgetKind::Health => {
+            parent.spawn((widget, HealthText {}, hud_text(asset_server, "HP 100", 30.), Label));
+        }
+        HudWidgetKind::Ammo => {
+            parent.spawn((widget, AmmoText {}, hud_text(asset_server, "25 / 25", 30.), Label));
+        }
+        HudWidgetKind::Score => {
+            parent.spawn((widget, ScoreText {}, hud_text(asset_server, "SCORE 0", 24.), Label));
+        }
+        HudWidgetKind::Armor
+        | HudWidgetKind::Reserve
+        | HudWidgetKind::FireMode
+        | HudWidgetKind::WeaponIcon
+        | HudWidgetKind::Timer => {
+            let font_size = if kind == HudWidgetKind::Timer { 30. } else { 22. };
+            parent.spawn((widget, HudText(kind), hud_text(asset_server, "", font_size), Label));
+        }
+        HudWidgetKind::Stamina => {
+            parent.spawn((widget, NodeBundle::default())).with_children(spawn_stamina_bar);
+        }
+        HudWidgetKind::KillFeed => {
+            parent.spawn((
+                widget,
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::FlexEnd,
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+        }
+        HudWidgetKind::Minimap => {
+            parent.spawn((
+                widget,
+                NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(MINIMAP_SIZE), Val::Px(MINIMAP_SIZE)),
+                        margin: UiRect::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.1, 0.1, 0.1, 0.5).into(),
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+// rebuilt from scratch whenever the layout changes, widgets refill themselves the next frame
+pub fn update_hud_layout(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    root_query: Query<Entity, With<HudRoot>>,
+    mut built: Local<Option<(Vec<HudWidgetLayout>, f32)>>,
+) {
+    if !settings.is_changed() && !root_query.is_empty() {
+        return;
+    }
+    let layout = (settings.hud_layout.clone(), settings.hud_safe_area);
+    if built.as_ref() == Some(&layout) && !root_query.is_empty() {
+        return;
+    }
+    for entity in root_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let margin = layout.1.clamp(0., 0.25) * 100.;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                ..default()
+            },
+            HudRoot {},
+        ))
+        .with_children(|root| {
+            for anchor in ALL_ANCHORS {
+                let (justify_content, align_items) = anchor.alignment();
+                root.spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(
+                            Val::Percent(100. - margin * 2.),
+                            Val::Percent(100. - margin * 2.),
+                        ),
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: Val::Percent(margin),
+                            top: Val::Percent(margin),
+                            ..default()
+                        },
+                        flex_direction: FlexDirection::Column,
+                        justify_content,
+                        align_items,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for widget in layout.0.iter() {
+                        if widget.anchor == anchor && widget.visible {
+                            spawn_widget(parent, &asset_server, widget.kind);
+                        }
+                    }
+                });
+            }
+        });
+    *built = Some(layout);
+}
+
+pub fn update_hud_text(
+    match_stats: Res<MatchStats>,
+    player_query: Query<&PlayerHealth>,
+    gun_query: Query<&GunController>,
+    mut text_query: Query<(&HudText, &mut Text, &mut Visibility)>,
+) {
+    let player_health = player_query.get_single().ok();
+    let gun_controller = gun_query.get_single().ok();
+    for (hud_text, mut text, mut visibility) in text_query.iter_mut() {
+        // none hides the widget, for values the current mode doesn't use
+        let value = match hud_text.0 {
+            HudWidgetKind::Armor => player_health
+                .filter(|player_health| player_health.max_armor > 0.)
+                .map(|player_health| format!("ARMOR {:.0}", player_health.armor.ceil())),
+            HudWidgetKind::Reserve => gun_controller
+                .and_then(|gun_controller| gun_controller.reserve_ammo)
+                .map(|reserve| format!("RESERVE {}", reserve)),
+            HudWidgetKind::FireMode => {
+                gun_controller.map(|gun_controller| gun_controller.fire_mode.label().to_string())
+            }
+            // there are no weapon icons yet, so it shows the weapon's name
+            HudWidgetKind::WeaponIcon => {
+                gun_controller.map(|gun_controller| gun_controller.definition.to_uppercase())
+            }
+            HudWidgetKind::Timer => {
+                let seconds = match_stats.time_remaining().ceil() as u32;
+                Some(format!("{}:{:02}", seconds / 60, seconds % 60))
+            }
+            _ => None,
+        };
+        let target_visibility = if value.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != target_visibility {
+            *visibility = target_visibility;
+        }
+        if let Some(value) = value {
+            if text.sections[0].value != value {
+                text.sections[0].value = value;
+            }
+        }
+    }
+}
+
+// enemies around the player, turned so straight up is the way the player faces
+pub fn update_minimap(
+    mut commands: Commands,
+    player_query: Query<(&GlobalTransform, &FPSCamera)>,
+    enemy_query: Query<(&GlobalTransform, &Enemy)>,
+    minimap_query: Query<(Entity, &HudWidget, Option<&Children>)>,
+    mut dot_query: Query<&mut Style, With<MinimapDot>>,
+) {
+    let Ok((player_transform, camera)) = player_query.get_single() else {
+        return;
+    };
+    let enemies: Vec<Vec3> = enemy_query
+        .iter()
+        .filter(|(_, enemy)| enemy.health > 0.)
+        .map(|(transform, _)| transform.translation())
+        .collect();
+    let rotation = Quat::from_rotation_y(camera.rotation.y);
+    let forward = rotation * Vec3::NEG_Z;
+    let right = rotation * Vec3::X;
+    let dot_size = 6.;
+    let center = MINIMAP_SIZE / 2.;
+
+    for (minimap, widget, children) in minimap_query.iter() {
+        if widget.kind != HudWidgetKind::Minimap {
+            continue;
+        }
+        let dots: Vec<Entity> = children.map_or(Vec::new(), |children| children.to_vec());
+        // keep one dot per enemy, the player's own sits in the middle as the first
+        if dots.len() != enemies.len() + 1 {
+            commands.entity(minimap).despawn_descendants();
+            commands.entity(minimap).with_children(|parent| {
+                for index in 0..enemies.len() + 1 {
+                    let color = if index == 0 {
+                        Color::WHITE
+                    } else {
+                        Color::rgb(1., 0.25, 0.2)
+                    };
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(dot_size), Val::Px(dot_size)),
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    left: Val::Px(center - dot_size / 2.),
+                                    top: Val::Px(center - dot_size / 2.),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            background_color: color.into(),
+                            ..default()
+                        },
+                        MinimapDot {},
+                    ));
+                }
+            });
+            continue;
+        }
+        for (dot, enemy) in dots.iter().skip(1).zip(enemies.iter()) {
+            let Ok(mut style) = dot_query.get_mut(*dot) else {
+                continue;
+            };
+            let offset = *enemy - player_transform.translation();
+            let position = Vec2::new(offset.dot(right), -offset.dot(forward)) / MINIMAP_RANGE;
+            // anything out of range waits on the edge
+            let position = position.clamp_length_max(1.) * (center - dot_size / 2.);
+            style.position.left = Val::Px(center + position.x - dot_size / 2.);
+            style.position.top = Val::Px(center + position.y - dot_size / 2.);
+        }
+    }
+}
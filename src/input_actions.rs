@@ -0,0 +1,537 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Fire,
+    Aim,
+    Reload,
+    SwitchWeapon,
+    FireMode,
+    Crouch,
+    Sprint,
+    LeanLeft,
+    LeanRight,
+    Spectate,
+    Pause,
+    ToggleCursorLock,
+    SpectatorNext,
+    SpectatorPrevious,
+    SpectatorMode,
+    ToggleRecoilEditor,
+    EditPattern,
+    RecordPattern,
+    PracticePattern,
+    SavePattern,
+}
+
+pub const ALL_ACTIONS: [Action; 25] = [
+    Action::MoveForward,
+    Action::MoveBack,
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::Jump,
+    Action::Fire,
+    Action::Aim,
+    Action::Reload,
+    Action::SwitchWeapon,
+    Action::FireMode,
+    Action::Crouch,
+    Action::Sprint,
+    Action::LeanLeft,
+    Action::LeanRight,
+    Action::Spectate,
+    Action::Pause,
+    Action::ToggleCursorLock,
+    Action::SpectatorNext,
+    Action::SpectatorPrevious,
+    Action::SpectatorMode,
+    Action::ToggleRecoilEditor,
+    Action::EditPattern,
+    Action::RecordPattern,
+    Action::PracticePattern,
+    Action::SavePattern,
+];
+
+// actions that can't be used at the same time are free to share an input
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ActionContext {
+    Always,
+    Playing,
+    Spectating,
+}
+
+impl ActionContext {
+    pub fn overlaps(&self, other: ActionContext) -> bool {
+        *self == ActionContext::Always || other == ActionContext::Always || *self == other
+    }
+}
+
+impl Action {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::MoveForward => "MOVE FORWARD",
+            Action::MoveBack => "MOVE BACK",
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::Jump => "JUMP",
+            Action::Fire => "FIRE",
+            Action::Aim => "AIM",
+            Action::Reload => "RELOAD",
+            Action::SwitchWeapon => "SWITCH WEAPON",
+            Action::FireMode => "FIRE MODE",
+            Action::Crouch => "CROUCH",
+            Action::Sprint => "SPRINT",
+            Action::LeanLeft => "LEAN LEFT",
+            Action::LeanRight => "LEAN RIGHT",
+            Action::Spectate => "SPECTATE",
+            Action::Pause => "PAUSE",
+            Action::ToggleCursorLock => "CURSOR LOCK",
+            Action::SpectatorNext => "SPECTATE NEXT",
+            Action::SpectatorPrevious => "SPECTATE PREVIOUS",
+            Action::SpectatorMode => "SPECTATOR CAMERA",
+            Action::ToggleRecoilEditor => "RECOIL EDITOR",
+            Action::EditPattern => "EDIT PATTERN",
+            Action::RecordPattern => "RECORD PATTERN",
+            Action::PracticePattern => "PRACTICE PATTERN",
+            Action::SavePattern => "SAVE PATTERN",
+        }
+    }
+    pub fn context(&self) -> ActionContext {
+        match self {
+            Action::Spectate | Action::Pause | Action::ToggleCursorLock => ActionContext::Always,
+            Action::SpectatorNext | Action::SpectatorPrevious | Action::SpectatorMode => {
+                ActionContext::Spectating
+            }
+            _ => ActionContext::Playing,
+        }
+    }
+    pub fn shares_context(&self, other: Action) -> bool {
+        self.context().overlaps(other.context())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, Binding::Gamepad(_))
+    }
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key).to_uppercase(),
+            Binding::Mouse(button) => format!("MOUSE {:?}", button).to_uppercase(),
+            Binding::Gamepad(button) => format!("PAD {:?}", button).to_uppercase(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ActionBinding {
+    pub action: Action,
+    pub bindings: Vec<Binding>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct InputBindings {
+    pub actions: Vec<ActionBinding>,
+
+    pub gamepad_dead_zone: f32,
+    // degrees per second at full stick deflection
+    pub gamepad_look_speed: f32,
+    // response curve exponent, 1 is linear and higher values give finer control near the center
+    pub gamepad_look_curve: f32,
+    // extra turn speed multiplier reached after holding the stick at its edge
+    pub gamepad_turn_boost: f32,
+    pub gamepad_turn_boost_time: f32,
+    pub gamepad_invert_y: bool,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let bind = |action: Action, bindings: &[Binding]| ActionBinding {
+            action,
+            bindings: bindings.to_vec(),
+        };
+        InputBindings {
+            actions: vec![
+                bind(Action::MoveForward, &[Binding::Key(KeyCode::W)]),
+                bind(Action::MoveBack, &[Binding::Key(KeyCode::S)]),
+                bind(Action::MoveLeft, &[Binding::Key(KeyCode::A)]),
+                bind(Action::MoveRight, &[Binding::Key(KeyCode::D)]),
+                bind(
+                    Action::Jump,
+                    &[
+                        Binding::Key(KeyCode::Space),
+                        Binding::Gamepad(GamepadButtonType::South),
+                    ],
+                ),
+                bind(
+                    Action::Fire,
+                    &[
+                        Binding::Mouse(MouseButton::Left),
+                        Binding::Gamepad(GamepadButtonType::RightTrigger2),
+                    ],
+                ),
+                bind(
+                    Action::Aim,
+                    &[
+                        Binding::Mouse(MouseButton::Right),
+                        Binding::Gamepad(GamepadButtonType::LeftTrigger2),
+                    ],
+                ),
+                bind(
+                    Action::Reload,
+                    &[
+                        Binding::Key(KeyCode::R),
+                        Binding::Gamepad(GamepadButtonType::West),
+                    ],
+                ),
+                bind(
+                    Action::SwitchWeapon,
+                    &[
+                        Binding::Key(KeyCode::X),
+                        Binding::Gamepad(GamepadButtonType::North),
+                    ],
+                ),
+                bind(Action::FireMode, &[Binding::Key(KeyCode::B)]),
+                bind(
+                    Action::Crouch,
+                    &[
+                        Binding::Key(KeyCode::LControl),
+                        Binding::Gamepad(GamepadButtonType::East),
+                    ],
+                ),
+                bind(
+                    Action::Sprint,
+                    &[
+                        Binding::Key(KeyCode::LShift),
+                        Binding::Gamepad(GamepadButtonType::LeftThumb),
+                    ],
+                ),
+                bind(
+                    Action::LeanLeft,
+                    &[
+                        Binding::Key(KeyCode::Q),
+                        Binding::Gamepad(GamepadButtonType::DPadLeft),
+                    ],
+                ),
+                bind(
+                    Action::LeanRight,
+                    &[
+                        Binding::Key(KeyCode::E),
+                        Binding::Gamepad(GamepadButtonType::DPadRight),
+                    ],
+                ),
+                bind(Action::Spectate, &[Binding::Key(KeyCode::V)]),
+                bind(
+                    Action::Pause,
+                    &[
+                        Binding::Key(KeyCode::Escape),
+                        Binding::Gamepad(GamepadButtonType::Start),
+                    ],
+                ),
+                bind(Action::ToggleCursorLock, &[Binding::Key(KeyCode::Tab)]),
+                bind(
+                    Action::SpectatorNext,
+                    &[
+                        Binding::Mouse(MouseButton::Left),
+                        Binding::Gamepad(GamepadButtonType::RightTrigger2),
+                    ],
+                ),
+                bind(
+                    Action::SpectatorPrevious,
+                    &[
+                        Binding::Mouse(MouseButton::Right),
+                        Binding::Gamepad(GamepadButtonType::LeftTrigger2),
+                    ],
+                ),
+                bind(
+                    Action::SpectatorMode,
+                    &[
+                        Binding::Mouse(MouseButton::Middle),
+                        Binding::Gamepad(GamepadButtonType::North),
+                    ],
+                ),
+                bind(Action::ToggleRecoilEditor, &[Binding::Key(KeyCode::F9)]),
+                bind(Action::EditPattern, &[Binding::Key(KeyCode::Key1)]),
+                bind(Action::RecordPattern, &[Binding::Key(KeyCode::Key2)]),
+                bind(Action::PracticePattern, &[Binding::Key(KeyCode::Key3)]),
+                bind(Action::SavePattern, &[Binding::Key(KeyCode::Return)]),
+            ],
+            gamepad_dead_zone: 0.15,
+            gamepad_look_speed: 220.,
+            gamepad_look_curve: 2.,
+            gamepad_turn_boost: 1.8,
+            gamepad_turn_boost_time: 0.4,
+            gamepad_invert_y: false,
+        }
+    }
+}
+
+impl InputBindings {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.actions
+            .iter()
+            .find(|action_binding| action_binding.action == action)
+            .map(|action_binding| action_binding.bindings.as_slice())
+            .unwrap_or(&[])
+    }
+
+    // actions added after a settings file was saved get their default bindings, skipping any
+    // input the player has already given to something else in use at the same time
+    pub fn add_missing_defaults(&mut self) {
+        let defaults = InputBindings::default();
+        for default_binding in defaults.actions {
+            let action = default_binding.action;
+            if self.actions.iter().any(|a| a.action == action) {
+                continue;
+            }
+            let taken = |binding: &Binding| {
+                self.actions
+                    .iter()
+                    .any(|a| a.action.shares_context(action) && a.bindings.contains(binding))
+            };
+            let bindings = default_binding
+                .bindings
+                .into_iter()
+                .filter(|binding| !taken(binding))
+                .collect();
+            self.actions.push(ActionBinding {
+                action: default_binding.action,
+                bindings,
+            });
+        }
+    }
+
+    // replaces the existing binding from the same kind of device, so rebinding a key
+    // keeps the gamepad button and the other way around. an input only drives one action at a
+    // time, so whatever had it before swaps to the old binding, or is left without one
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let index = match self.actions.iter().position(|a| a.action == action) {
+            Some(index) => index,
+            None => {
+                self.actions.push(ActionBinding {
+                    action,
+                    bindings: Vec::new(),
+                });
+                self.actions.len() - 1
+            }
+        };
+        let previous = self.actions[index]
+            .bindings
+            .iter()
+            .find(|existing| existing.is_gamepad() == binding.is_gamepad())
+            .copied();
+        for (other_index, other) in self.actions.iter_mut().enumerate() {
+            if other_index == index || !other.action.shares_context(action) {
+                continue;
+            }
+            let swap = previous.filter(|previous| !other.bindings.contains(previous));
+            for existing in other.bindings.iter_mut() {
+                if *existing == binding {
+                    *existing = swap.unwrap_or(binding);
+                }
+            }
+            if swap.is_none() {
+                other.bindings.retain(|existing| *existing != binding);
+            }
+        }
+        let bindings = &mut self.actions[index].bindings;
+        bindings.retain(|existing| existing.is_gamepad() != binding.is_gamepad());
+        bindings.insert(0, binding);
+    }
+}
+
+pub fn apply_dead_zone(stick: Vec2, dead_zone: f32) -> Vec2 {
+    let magnitude = stick.length();
+    if magnitude <= dead_zone || dead_zone >= 1. {
+        return Vec2::ZERO;
+    }
+    let scaled = ((magnitude - dead_zone) / (1. - dead_zone)).min(1.);
+    stick / magnitude * scaled
+}
+
+pub fn apply_look_curve(stick: Vec2, exponent: f32) -> Vec2 {
+    let magnitude = stick.length();
+    if magnitude == 0. {
+        return Vec2::ZERO;
+    }
+    stick / magnitude * magnitude.powf(exponent)
+}
+
+#[derive(Resource, Default)]
+pub struct InputActions {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    just_released: HashSet<Action>,
+
+    // x is strafe right, y is forward, length at most 1
+    pub move_axis: Vec2,
+    // gamepad look for this frame in radians, x is yaw and y is pitch
+    pub gamepad_look: Vec2,
+    pub turn_boost_timer: f32,
+}
+
+impl InputActions {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+    pub fn just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+}
+
+pub fn update_input_actions(
+    settings: Res<Settings>,
+    time: Res<Time>,
+    key: Res<Input<KeyCode>>,
+    mouse_button: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_button: Res<Input<GamepadButton>>,
+    gamepad_axis: Res<Axis<GamepadAxis>>,
+    mut actions: ResMut<InputActions>,
+) {
+    let bindings = &settings.bindings;
+    let gamepad = gamepads.iter().next();
+    let binding_pressed = |binding: &Binding| match binding {
+        Binding::Key(code) => key.pressed(*code),
+        Binding::Mouse(button) => mouse_button.pressed(*button),
+        Binding::Gamepad(button_type) => gamepad
+            .map(|gamepad| gamepad_button.pressed(GamepadButton::new(gamepad, *button_type)))
+            .unwrap_or(false),
+    };
+
+    let mut pressed = HashSet::new();
+    for action in ALL_ACTIONS {
+        if bindings.bindings(action).iter().any(binding_pressed) {
+            pressed.insert(action);
+        }
+    }
+    actions.just_pressed = pressed.difference(&actions.pressed).copied().collect();
+    actions.just_released = actions.pressed.difference(&pressed).copied().collect();
+    actions.pressed = pressed;
+
+    let mut move_axis = Vec2::ZERO;
+    if actions.pressed(Action::MoveForward) {
+        move_axis.y += 1.;
+    }
+    if actions.pressed(Action::MoveBack) {
+        move_axis.y -= 1.;
+    }
+    if actions.pressed(Action::MoveRight) {
+        move_axis.x += 1.;
+    }
+    if actions.pressed(Action::MoveLeft) {
+        move_axis.x -= 1.;
+    }
+
+    let mut gamepad_look = Vec2::ZERO;
+    if let Some(gamepad) = gamepad {
+        let stick = |x: GamepadAxisType, y: GamepadAxisType| {
+            Vec2::new(
+                gamepad_axis
+                    .get(GamepadAxis::new(gamepad, x))
+                    .unwrap_or(0.),
+                gamepad_axis
+                    .get(GamepadAxis::new(gamepad, y))
+                    .unwrap_or(0.),
+            )
+        };
+        let left_stick = apply_dead_zone(
+            stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            bindings.gamepad_dead_zone,
+        );
+        move_axis += left_stick;
+
+        let right_stick = apply_dead_zone(
+            stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+            bindings.gamepad_dead_zone,
+        );
+        if right_stick.length() > 0.95 {
+            actions.turn_boost_timer += time.delta_seconds();
+        } else {
+            actions.turn_boost_timer = 0.;
+        }
+        let boost_progress = if bindings.gamepad_turn_boost_time > 0. {
+            (actions.turn_boost_timer / bindings.gamepad_turn_boost_time).min(1.)
+        } else {
+            1.
+        };
+        let boost = 1. + (bindings.gamepad_turn_boost - 1.) * boost_progress;
+
+        let curved = apply_look_curve(right_stick, bindings.gamepad_look_curve);
+        gamepad_look = curved * bindings.gamepad_look_speed.to_radians() * boost * time.delta_seconds();
+        if bindings.gamepad_invert_y {
+            gamepad_look.y = -gamepad_look.y;
+        }
+    }
+
+    if move_axis.length() > 1. {
+        move_axis = move_axis.normalize();
+    }
+    actions.move_axis = move_axis;
+    actions.gamepad_look = gamepad_look;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_a_taken_input_swaps_it() {
+        let mut bindings = InputBindings::default();
+        bindings.rebind(Action::Jump, Binding::Key(KeyCode::R));
+        assert_eq!(bindings.bindings(Action::Jump)[0], Binding::Key(KeyCode::R));
+        assert!(bindings.bindings(Action::Reload).contains(&Binding::Key(KeyCode::Space)));
+        assert!(!bindings.bindings(Action::Reload).contains(&Binding::Key(KeyCode::R)));
+
+        // nothing to swap back, so the other action just loses it
+        bindings.actions.retain(|a| a.action != Action::Spectate);
+        bindings.rebind(Action::Spectate, Binding::Key(KeyCode::Space));
+        assert_eq!(
+            bindings.bindings(Action::Reload),
+            &[Binding::Gamepad(GamepadButtonType::West)]
+        );
+    }
+
+    #[test]
+    fn spectator_actions_share_inputs_with_playing() {
+        let mut bindings = InputBindings::default();
+        bindings.rebind(Action::Fire, Binding::Mouse(MouseButton::Middle));
+        assert_eq!(
+            bindings.bindings(Action::SpectatorMode)[0],
+            Binding::Mouse(MouseButton::Middle)
+        );
+        assert_eq!(
+            bindings.bindings(Action::SpectatorNext)[0],
+            Binding::Mouse(MouseButton::Left)
+        );
+
+        // a settings file from before the spectator actions still gets their mouse buttons
+        bindings.actions.retain(|a| a.action.context() != ActionContext::Spectating);
+        bindings.add_missing_defaults();
+        assert_eq!(
+            bindings.bindings(Action::SpectatorPrevious)[0],
+            Binding::Mouse(MouseButton::Right)
+        );
+    }
+}
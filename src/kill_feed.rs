@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+
+use crate::hud::{HudWidget, HudWidgetKind};
+
+// seconds a kill stays in the feed, the last of them fading it out
+const KILL_FEED_LIFETIME: f32 = 5.;
+const KILL_FEED_FADE: f32 = 1.;
+const KILL_FEED_MAX_ENTRIES: usize = 5;
+
+// sent when something dies, for the feed and the match log. there is no wallbang flag while
+// bullets stop at the first thing they hit
+#[derive(Clone, Debug)]
+pub struct KillEvent {
+    pub killer: String,
+    pub victim: String,
+    pub weapon: String,
+    pub headshot: bool,
+    // meters from the shooter to the hit
+    pub distance: f32,
+    pub position: Vec3,
+}
+
+#[derive(Component)]
+pub struct KillFeedEntry {
+    pub age: f32,
+}
+
+fn kill_feed_sections(asset_server: &AssetServer, kill: &KillEvent) -> Vec<TextSection> {
+    let font = asset_server.load("font.ttf");
+    let section = |value: String, color: Color| {
+        TextSection::new(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size: 20.,
+                color,
+            },
+        )
+    };
+    // there are no weapon icons yet, so the weapon's name stands in for one
+    let mut sections = vec![
+        section(kill.killer.clone(), Color::WHITE),
+        section(format!("  [{}]", kill.weapon.to_uppercase()), Color::GRAY),
+    ];
+    if kill.headshot {
+        sections.push(section(" HEAD".to_string(), Color::YELLOW));
+    }
+    sections.push(section(format!("  {}", kill.victim), Color::rgb(1., 0.35, 0.3)));
+    sections
+}
+
+pub fn update_kill_feed(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut kill_events: EventReader<KillEvent>,
+    feed_query: Query<(Entity, &HudWidget, Option<&Children>)>,
+    mut entry_query: Query<(&mut KillFeedEntry, &mut Text)>,
+) {
+    let kills: Vec<&KillEvent> = kill_events.iter().collect();
+    for (feed, widget, children) in feed_query.iter() {
+        if widget.kind != HudWidgetKind::KillFeed {
+            continue;
+        }
+        let entries: Vec<Entity> = children.map_or(Vec::new(), |children| children.to_vec());
+        let mut remaining = entries.len();
+        for entry in entries.iter() {
+            let Ok((mut kill_feed_entry, mut text)) = entry_query.get_mut(*entry) else {
+                continue;
+            };
+            kill_feed_entry.age += time.delta_seconds();
+            // the oldest make room once the feed is full
+            let pushed_out = remaining + kills.len() > KILL_FEED_MAX_ENTRIES;
+            if kill_feed_entry.age >= KILL_FEED_LIFETIME || pushed_out {
+                commands.entity(*entry).despawn_recursive();
+                remaining -= 1;
+                continue;
+            }
+            let alpha = ((KILL_FEED_LIFETIME - kill_feed_entry.age) / KILL_FEED_FADE).min(1.);
+            for section in text.sections.iter_mut() {
+                section.style.color.set_a(alpha);
+            }
+        }
+        // newest at the bottom
+        commands.entity(feed).with_children(|parent| {
+            for kill in kills.iter().rev().take(KILL_FEED_MAX_ENTRIES).rev() {
+                parent.spawn((
+                    TextBundle::from_sections(kill_feed_sections(&asset_server, kill)).with_style(
+                        Style {
+                            margin: UiRect::all(Val::Px(2.0)),
+                            ..default()
+                        },
+                    ),
+                    KillFeedEntry { age: 0. },
+                ));
+            }
+        });
+    }
+}
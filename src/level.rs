@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::config_file::load_asset_toml;
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct LevelMetadata {
+    pub ladders: Vec<LadderVolume>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct LadderVolume {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+#[derive(Component)]
+pub struct Ladder {}
+
+// metadata uses the map's z-up coordinates like the tables in setup_map
+pub fn map_to_world(position: [f32; 3]) -> Vec3 {
+    Vec3::new(position[0], position[2], -position[1])
+}
+
+pub fn load_level_metadata(map: &str) -> Result<LevelMetadata, String> {
+    load_asset_toml(&format!("levels/{}.toml", map))
+}
+
+pub fn spawn_ladders(commands: &mut Commands, metadata: &LevelMetadata) {
+    for ladder in &metadata.ladders {
+        let a = map_to_world(ladder.min);
+        let b = map_to_world(ladder.max);
+        let half_extents = (a - b).abs() / 2.;
+        commands.spawn((
+            TransformBundle::from(Transform::from_translation((a + b) / 2.)),
+            Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+            Sensor,
+            Ladder {},
+        ));
+    }
+}
@@ -0,0 +1,244 @@
+use bevy::{asset::LoadState, prelude::*};
+
+use crate::{
+    game_state::AppState,
+    gun_control::GunController,
+    menu::{spawn_button, MenuButton},
+    setup_map,
+    sound::LANDING_SOUND,
+    weapon_definition::{load_weapon_definition, weapon_definition_file},
+    MapStatus,
+};
+
+pub const REQUIRED_ASSETS: [&str; 12] = [
+    "map.glb#Mesh0/Primitive0",
+    "gun.glb#Scene0",
+    "gun.glb#Animation0",
+    "gun.glb#Animation1",
+    "gun.glb#Animation2",
+    "person.glb#Scene0",
+    "person.glb#Animation0",
+    "person.glb#Animation1",
+    "sand.png",
+    "bullet_hole.png",
+    "font.ttf",
+    LANDING_SOUND,
+];
+
+// the game still runs without these, so a failure is only logged
+pub const OPTIONAL_ASSETS: [&str; 1] = ["normal_map.png"];
+
+pub struct TrackedAsset {
+    pub path: &'static str,
+    pub handle: HandleUntyped,
+    pub required: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct AssetsLoading(pub Vec<TrackedAsset>);
+
+#[derive(Resource, Default)]
+pub struct LoadingError {
+    pub asset: String,
+    pub reason: String,
+}
+
+#[derive(Component)]
+pub struct LoadingScreen {}
+
+#[derive(Component)]
+pub struct LoadingProgressBar {}
+
+#[derive(Component)]
+pub struct LoadingProgressText {}
+
+pub fn start_loading(asset_server: Res<AssetServer>, mut loading: ResMut<AssetsLoading>) {
+    for path in REQUIRED_ASSETS {
+        loading.0.push(TrackedAsset {
+            path,
+            handle: asset_server.load_untyped(path),
+            required: true,
+        });
+    }
+    for path in OPTIONAL_ASSETS {
+        loading.0.push(TrackedAsset {
+            path,
+            handle: asset_server.load_untyped(path),
+            required: false,
+        });
+    }
+}
+
+pub fn setup_loading_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("font.ttf");
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.05, 0.05, 0.05).into(),
+                ..default()
+            },
+            LoadingScreen {},
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "LOADING",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                }),
+                LoadingProgressText {},
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(400.0), Val::Px(16.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.2, 0.2, 0.2).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: Color::WHITE.into(),
+                            ..default()
+                        },
+                        LoadingProgressBar {},
+                    ));
+                });
+        });
+}
+
+pub fn check_assets_ready(
+    commands: Commands,
+    map_status: ResMut<MapStatus>,
+    server: Res<AssetServer>,
+    meshes: ResMut<Assets<Mesh>>,
+    materials: ResMut<Assets<StandardMaterial>>,
+    loading: Res<AssetsLoading>,
+    mut loading_error: ResMut<LoadingError>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut bar_query: Query<&mut Style, With<LoadingProgressBar>>,
+    mut text_query: Query<&mut Text, With<LoadingProgressText>>,
+    mut gun_query: Query<&mut GunController>,
+) {
+    let mut finished = 0;
+    for asset in loading.0.iter() {
+        match server.get_load_state(&asset.handle) {
+            LoadState::Loaded => finished += 1,
+            LoadState::Failed if asset.required => {
+                error!("failed to load required asset {}", asset.path);
+                loading_error.asset = asset.path.to_string();
+                loading_error.reason = "the file is missing or could not be read".to_string();
+                next_state.set(AppState::LoadingFailed);
+                return;
+            }
+            LoadState::Failed => {
+                warn!("failed to load optional asset {}", asset.path);
+                finished += 1;
+            }
+            _ => {}
+        }
+    }
+
+    let progress = finished as f32 / loading.0.len() as f32;
+    for mut style in bar_query.iter_mut() {
+        style.size.width = Val::Percent(progress * 100.);
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!("LOADING {} / {}", finished, loading.0.len());
+    }
+
+    if finished == loading.0.len() {
+        for mut gun_controller in gun_query.iter_mut() {
+            match load_weapon_definition(&gun_controller.definition) {
+                Ok(definition) => {
+                    gun_controller.spray_pattern = definition.spray_pattern();
+                    gun_controller.recoil = definition.recoil;
+                    gun_controller.sway_settings = definition.sway;
+                }
+                Err(reason) => {
+                    error!("failed to load weapon {}: {}", gun_controller.definition, reason);
+                    loading_error.asset = weapon_definition_file(&gun_controller.definition);
+                    loading_error.reason = reason;
+                    next_state.set(AppState::LoadingFailed);
+                    return;
+                }
+            }
+        }
+        match setup_map(commands, map_status, server, meshes, materials) {
+            Ok(()) => next_state.set(AppState::MainMenu),
+            Err(reason) => {
+                error!("failed to set up the map: {}", reason);
+                loading_error.asset = "map".to_string();
+                loading_error.reason = reason;
+                next_state.set(AppState::LoadingFailed);
+            }
+        }
+    }
+}
+
+pub fn setup_loading_failed_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    loading_error: Res<LoadingError>,
+) {
+    let font = asset_server.load("font.ttf");
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.05, 0.05, 0.05).into(),
+                ..default()
+            },
+            LoadingScreen {},
+        ))
+        .with_children(|parent| {
+            // the font itself may be what failed, in which case only the button box shows
+            parent.spawn(
+                TextBundle::from_section(
+                    format!(
+                        "FAILED TO LOAD {}\n{}",
+                        loading_error.asset, loading_error.reason
+                    ),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 30.0,
+                        color: Color::rgb(1., 0.3, 0.3),
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+            spawn_button(parent, font, "QUIT", MenuButton::Quit);
+        });
+}
@@ -1,39 +1,59 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::{
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+
+use crate::input_actions::{Action, InputActions};
 #[derive(Resource)]
 pub struct CursorLockState {
     pub state: bool,
     pub allow_lock: bool,
 }
+
+// windows can only confine the cursor and macos can only lock it, either way mouse motion
+// comes from raw device deltas so the cursor never needs to be recentred
+pub fn locked_grab_mode() -> CursorGrabMode {
+    if cfg!(target_os = "windows") {
+        CursorGrabMode::Confined
+    } else {
+        CursorGrabMode::Locked
+    }
+}
+
+pub fn set_cursor_grab(window: &mut Window, locked: bool) {
+    let grab_mode = if locked {
+        locked_grab_mode()
+    } else {
+        CursorGrabMode::None
+    };
+    window.cursor.grab_mode = grab_mode;
+    window.cursor.visible = !locked;
+}
+
 pub fn lock_cursor_position(
     mut primary_query: Query<&mut Window, With<PrimaryWindow>>,
     mut cursor_lock_state: ResMut<CursorLockState>,
-    btn: Res<Input<MouseButton>>,
-    key: Res<Input<KeyCode>>,
+    actions: Res<InputActions>,
 ) {
     let Ok(mut primary) = primary_query.get_single_mut() else
     {
         return;
     };
 
-    if key.just_pressed(KeyCode::Tab) {
+    if actions.just_pressed(Action::ToggleCursorLock) {
         cursor_lock_state.allow_lock = !cursor_lock_state.allow_lock;
     }
     if cursor_lock_state.allow_lock {
-        if btn.just_pressed(MouseButton::Left) {
+        if actions.just_pressed(Action::Fire) {
             cursor_lock_state.state = true;
         }
     }
 
-    if key.just_pressed(KeyCode::Escape) {
-        cursor_lock_state.state = false;
-    }
-
-    if cursor_lock_state.state {
-        primary.cursor.visible = false;
-        let width = primary.width();
-        let height = primary.height();
-        primary.set_cursor_position(Some(Vec2::new(width / 2., height / 2.)));
-    } else {
-        primary.cursor.visible = true;
+    // only touch the window when something changed so the grab isn't re-requested every frame
+    let locked = cursor_lock_state.state;
+    if (primary.cursor.grab_mode != CursorGrabMode::None) != locked
+        || primary.cursor.visible == locked
+    {
+        set_cursor_grab(&mut primary, locked);
     }
 }
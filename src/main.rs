@@ -1,10 +1,14 @@
-use std::sync::atomic::AtomicBool;
-
 use bevy::{
-    core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
+    core_pipeline::{
+        bloom::BloomSettings, clear_color::ClearColorConfig, core_3d::Camera3dDepthLoadOp,
+        tonemapping::Tonemapping,
+    },
     prelude::*,
-    render::{view::NoFrustumCulling, render_resource::Face},
-    window::{PrimaryWindow, WindowMode, WindowResolution},
+    render::{
+        render_resource::Face,
+        view::{NoFrustumCulling, RenderLayers},
+    },
+    window::PrimaryWindow,
 };
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_rapier3d::prelude::*;
@@ -12,16 +16,47 @@ use bevy_rapier3d::prelude::*;
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 pub mod bloom;
 pub mod bullet_tracer;
+pub mod camera_effects;
+pub mod config_file;
+pub mod controls_menu;
+pub mod crosshair;
 pub mod enemy;
 pub mod fps_camera;
 pub mod fps_movement;
 pub mod fps_shooting;
+pub mod game_mode;
+pub mod game_state;
 pub mod gun_control;
+pub mod hud;
+pub mod input_actions;
+pub mod kill_feed;
+pub mod level;
+pub mod loading;
 pub mod lock_cursor;
+pub mod match_log;
+pub mod menu;
+pub mod mouse_look;
+pub mod personal_best;
+pub mod player_health;
+pub mod random;
+pub mod recoil;
+pub mod recoil_editor;
+pub mod results_ui;
 pub mod rotation_operations;
 pub mod score_ui;
+pub mod sensitivity;
+pub mod settings;
+pub mod sound;
+pub mod spectator;
+pub mod stamina_ui;
+pub mod third_person;
 pub mod vector_operations;
+pub mod viewmodel;
+pub mod weapon_definition;
+pub mod weapon_sway;
 fn main() {
+    let settings = settings::Settings::load();
+    let primary_window = settings.window();
     App::new()
         .insert_resource(ClearColor(Color::rgb(0.5, 0.8, 0.9)))
         .insert_resource(lock_cursor::CursorLockState {
@@ -29,37 +64,201 @@ fn main() {
             allow_lock: true,
         })
         .insert_resource(MapStatus{loaded : false})
-        .add_system(fps_movement::player_movement)
-        .add_system(fps_camera::move_camera.after(fps_movement::player_movement))
-        .add_system(gun_control::update_gun_control.after(fps_camera::move_camera))
+        .insert_resource(menu::new_match(game_mode::GameMode::AimTrainer))
+        .insert_resource(personal_best::PersonalBests::load())
+        .init_resource::<personal_best::LastMatchResult>()
+        .init_resource::<spectator::Spectator>()
+        .init_resource::<random::GameRng>()
+        .add_state::<game_state::AppState>()
+        .add_systems(
+            (
+                fps_movement::player_movement.run_if(spectator::not_spectating),
+                fps_camera::move_camera
+                    .after(fps_movement::player_movement)
+                    .run_if(spectator::not_spectating),
+                fps_camera::update_aim_fov
+                    .after(fps_shooting::update_bullet_params)
+                    .before(fps_camera::move_camera),
+                gun_control::update_gun_control
+                    .after(fps_camera::update_lean)
+                    .after(fps_shooting::update_shots),
+                fps_shooting::update_shots.run_if(spectator::not_spectating),
+                fps_shooting::update_bullet_params
+                    .after(fps_shooting::update_shots)
+                    .run_if(spectator::not_spectating)
+                    .run_if(recoil_editor::not_editing_pattern),
+                fps_shooting::play_gun_animations.after(fps_shooting::update_shots),
+                fps_shooting::update_targets,
+                lock_cursor::lock_cursor_position,
+                bullet_tracer::update_tracers,
+                score_ui::update_score,
+                gun_control::update_ammo_count_text,
+                gun_control::apply_movement_inaccuracy.before(fps_shooting::update_shots),
+                game_mode::update_match_timer,
+                stamina_ui::update_stamina_bar.after(fps_movement::player_movement),
+            )
+                .in_set(OnUpdate(game_state::AppState::InGame)),
+        )
+        .add_event::<fps_movement::PlayerLanded>()
+        .add_event::<camera_effects::CameraTrauma>()
+        .add_systems(
+            (
+                camera_effects::apply_camera_events.before(camera_effects::update_camera_effects),
+                camera_effects::update_camera_effects.before(fps_camera::move_camera),
+                fps_camera::update_lean
+                    .after(fps_camera::move_camera)
+                    .run_if(spectator::not_spectating),
+                player_health::apply_fall_damage.before(camera_effects::apply_camera_events),
+                player_health::update_health_text.after(player_health::apply_fall_damage),
+                sound::play_landing_sound,
+                spectator::toggle_spectator.before(spectator::update_spectator),
+                spectator::update_spectator.after(fps_camera::update_lean),
+                third_person::update_third_person_camera
+                    .after(fps_camera::update_lean)
+                    .run_if(spectator::not_spectating),
+                third_person::update_player_model.after(spectator::update_spectator),
+                viewmodel::assign_viewmodel_layer,
+                viewmodel::update_viewmodel_camera.after(third_person::update_player_model),
+                recoil::update_recoil
+                    .after(fps_shooting::update_shots)
+                    .before(fps_camera::move_camera),
+                recoil::update_recoil_indicator.after(fps_camera::update_lean),
+                weapon_sway::update_weapon_sway
+                    .after(fps_shooting::update_bullet_params)
+                    .before(gun_control::update_gun_control),
+            )
+                .after(fps_movement::player_movement)
+                .in_set(OnUpdate(game_state::AppState::InGame)),
+        )
+        .add_event::<fps_shooting::ShotFired>()
+        .init_resource::<recoil_editor::RecoilEditor>()
+        .add_event::<kill_feed::KillEvent>()
+        .init_resource::<match_log::MatchLog>()
+        .add_systems(
+            (
+                recoil_editor::toggle_recoil_editor.before(recoil_editor::update_recoil_editor),
+                recoil_editor::update_recoil_editor.after(fps_shooting::update_shots),
+                recoil_editor::update_pattern_markers.after(recoil_editor::update_recoil_editor),
+                recoil_editor::update_pattern_editor_text
+                    .after(recoil_editor::update_recoil_editor),
+                crosshair::update_crosshair.after(fps_shooting::update_bullet_params),
+                hud::update_hud_text,
+                hud::update_minimap,
+                kill_feed::update_kill_feed,
+            )
+                .in_set(OnUpdate(game_state::AppState::InGame)),
+        )
+        .add_system(
+            enemy::rotate_to_player
+                .in_base_set(CoreSet::PostUpdate)
+                .run_if(in_state(game_state::AppState::InGame)),
+        )
+        // in the same frame as the kill, a match ending on it has left InGame by the next update
+        .add_system(
+            match_log::record_kills
+                .in_base_set(CoreSet::PostUpdate)
+                .after(enemy::rotate_to_player)
+                .run_if(in_state(game_state::AppState::InGame)),
+        )
+        .add_system(
+            fps_movement::keep_controller_upright
+                .in_base_set(CoreSet::PostUpdate)
+                .before(PhysicsSet::SyncBackend),
+        )
         .add_system(bloom::update_bloom_settings)
-        .add_system(fps_shooting::update_shots)
-        .add_system(fps_shooting::update_bullet_params.after(fps_shooting::update_shots))
-        .add_system(fps_shooting::play_gun_animations.after(fps_shooting::update_shots))
-        .add_system(fps_shooting::update_targets)
-        .add_system(lock_cursor::lock_cursor_position)
-        .add_system(bullet_tracer::update_tracers)
-        .add_system(score_ui::update_score)
+        .add_system(hud::update_ui_scale)
+        .add_system(hud::update_hud_layout)
         .add_system(link_animations)
-        .add_system(gun_control::update_ammo_count_text)
-        .add_system(gun_control::apply_movement_inaccuracy.before(fps_shooting::update_shots))
-        .add_system(enemy::rotate_to_player.in_base_set(CoreSet::PostUpdate))
+        .add_system(game_state::toggle_pause)
+        .add_system(menu::handle_menu_buttons)
+        .insert_resource(settings)
+        .init_resource::<settings::SettingsMenu>()
+        .add_system(settings::apply_settings)
+        .add_system(settings::handle_setting_buttons)
+        .add_system(settings::handle_settings_page_buttons)
+        .add_system(settings::handle_setting_value_buttons)
+        .add_system(settings::edit_setting_value.after(settings::handle_setting_value_buttons))
+        .add_system(
+            settings::update_settings_screen
+                .after(settings::handle_setting_buttons)
+                .after(settings::edit_setting_value),
+        )
+        .add_system(settings::close_settings_menu.in_schedule(OnExit(game_state::AppState::MainMenu)))
+        .add_system(settings::close_settings_menu.in_schedule(OnExit(game_state::AppState::Paused)))
+        .init_resource::<input_actions::InputActions>()
+        .add_system(
+            input_actions::update_input_actions
+                .in_base_set(CoreSet::PreUpdate)
+                .after(bevy::input::InputSystem),
+        )
+        .init_resource::<controls_menu::ControlsMenu>()
+        .add_system(
+            controls_menu::handle_controls_page_buttons.before(controls_menu::capture_rebind),
+        )
+        .add_system(controls_menu::handle_rebind_buttons)
+        .add_system(controls_menu::capture_rebind.after(controls_menu::handle_rebind_buttons))
+        .add_system(controls_menu::update_controls_screen.after(controls_menu::capture_rebind))
+        .add_system(menu::setup_main_menu.in_schedule(OnEnter(game_state::AppState::MainMenu)))
+        .add_system(
+            game_state::despawn_screen::<menu::MainMenuScreen>
+                .in_schedule(OnExit(game_state::AppState::MainMenu)),
+        )
+        .add_system(
+            game_state::reset_match
+                .run_if(game_state::is_new_match)
+                .in_schedule(OnEnter(game_state::AppState::InGame)),
+        )
+        .add_system(
+            match_log::start_match_log
+                .run_if(game_state::is_new_match)
+                .in_schedule(OnEnter(game_state::AppState::InGame)),
+        )
+        .add_system(game_state::release_cursor.in_schedule(OnExit(game_state::AppState::InGame)))
+        .add_systems(
+            (game_state::pause_world, menu::setup_pause_menu)
+                .in_schedule(OnEnter(game_state::AppState::Paused)),
+        )
+        .add_systems(
+            (
+                game_state::resume_world,
+                game_state::despawn_screen::<menu::PauseScreen>,
+            )
+                .in_schedule(OnExit(game_state::AppState::Paused)),
+        )
+        .add_systems(
+            (
+                personal_best::record_personal_best,
+                match_log::save_match_log,
+                results_ui::spawn_results_screen,
+            )
+                .chain()
+                .in_schedule(OnEnter(game_state::AppState::PostMatch)),
+        )
+        .add_system(
+            game_state::despawn_screen::<results_ui::ResultsScreen>
+                .in_schedule(OnExit(game_state::AppState::PostMatch)),
+        )
         .add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
                 .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        position: WindowPosition::Centered(MonitorSelection::Primary),
-                        resolution: WindowResolution::new(1920., 1080.),
-                        mode: WindowMode::BorderlessFullscreen,
-                        ..default()
-                    }),
+                    primary_window: Some(primary_window),
                     ..default()
                 }),
         )
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::default().with_physics_scale(10.))
-        .add_system(check_assets_ready)
-        .init_resource::<AssetsLoading>()
+        .init_resource::<loading::AssetsLoading>()
+        .init_resource::<loading::LoadingError>()
+        .add_system(loading::check_assets_ready.in_set(OnUpdate(game_state::AppState::Loading)))
+        .add_system(loading::setup_loading_screen.in_schedule(OnEnter(game_state::AppState::Loading)))
+        .add_system(
+            game_state::despawn_screen::<loading::LoadingScreen>
+                .in_schedule(OnExit(game_state::AppState::Loading)),
+        )
+        .add_system(
+            loading::setup_loading_failed_screen
+                .in_schedule(OnEnter(game_state::AppState::LoadingFailed)),
+        )
         /*
         .add_plugin(RapierDebugRenderPlugin {
             always_on_top: true,
@@ -71,38 +270,10 @@ fn main() {
         .add_startup_system(setup)
         .add_startup_system(setup_ui)
         
-        .add_startup_system(setup_physics)
+        .add_startup_system(loading::start_loading)
         .run();
 }
 
-#[derive(Resource,Default)]
-pub struct AssetsLoading(Vec<HandleUntyped>);
-
-fn check_assets_ready(
-    mut map_status : ResMut<MapStatus>,
-    commands: Commands,
-    server: Res<AssetServer>,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<StandardMaterial>>,
-    loading: Res<AssetsLoading>,
-) {
-    use bevy::asset::LoadState;
-    static SETUP_PHYSICS_CALLED: AtomicBool = AtomicBool::new(false);
-    match server.get_group_load_state(loading.0.iter().map(|h| h.id())) {
-        LoadState::Failed => {
-            // one of our assets had an error
-        }
-        LoadState::Loaded => {
-            if !SETUP_PHYSICS_CALLED.load(std::sync::atomic::Ordering::Relaxed) {
-                setup_map(commands, map_status,server, meshes, materials);
-                SETUP_PHYSICS_CALLED.store(true, std::sync::atomic::Ordering::Relaxed);
-            }
-        }
-        _ => {
-            // NotLoaded/Loading: not fully ready yet
-        }
-    }
-}
 #[derive(Resource)]
 pub struct MapStatus
 {
@@ -143,28 +314,33 @@ pub fn link_animations(
         }
     }
 }
-fn setup_map(mut commands: Commands,
+pub fn setup_map(mut commands: Commands,
     mut map_status : ResMut<MapStatus>,
     asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,)
+    mut materials: ResMut<Assets<StandardMaterial>>,) -> Result<(), String>
 {
     let mesh_handle: Handle<Mesh> = asset_server.load("map.glb#Mesh0/Primitive0");
 
-    let m = meshes.get(&mesh_handle);
-    let mut map_mesh = m.unwrap().clone();
-    Mesh::generate_tangents(&mut map_mesh);
-
-    
-    let x_shape = Collider::from_bevy_mesh(m.unwrap(), &ComputedColliderShape::TriMesh).unwrap();
-    if Collider::from_bevy_mesh(m.unwrap(), &ComputedColliderShape::TriMesh).is_none()
-    {
-        println!("{}","the mesh failed to load");
+    let Some(m) = meshes.get(&mesh_handle) else {
+        return Err("map.glb has no Mesh0/Primitive0".to_string());
+    };
+    let mut map_mesh = m.clone();
+    if let Err(err) = Mesh::generate_tangents(&mut map_mesh) {
+        warn!("could not generate map tangents: {}", err);
     }
+
+    let Some(x_shape) = Collider::from_bevy_mesh(m, &ComputedColliderShape::TriMesh) else {
+        return Err("could not build a collider from the map mesh".to_string());
+    };
     let texture_handle = asset_server.load("sand.png");
-    let normal_handle = asset_server.load("normal_map.png");
+    let normal_handle: Handle<Image> = asset_server.load("normal_map.png");
+    let normal_map_texture = match asset_server.get_load_state(&normal_handle) {
+        bevy::asset::LoadState::Loaded => Some(normal_handle),
+        _ => None,
+    };
     let wall_mat = materials.add(StandardMaterial {
-        normal_map_texture : Some(normal_handle.clone()),
+        normal_map_texture,
         base_color: Color::WHITE,
         perceptual_roughness :0.5,
         base_color_texture: Some(texture_handle.clone()),
@@ -172,6 +348,9 @@ fn setup_map(mut commands: Commands,
         unlit: false,
         ..default()
     });
+    let level_metadata = level::load_level_metadata("map")?;
+    level::spawn_ladders(&mut commands, &level_metadata);
+
     let mut light_vec = Vec::new();
     light_vec.push(Vec3::new(1.,0.,2.));
     light_vec.push(Vec3::new(37.,-7.,3.));
@@ -187,7 +366,7 @@ fn setup_map(mut commands: Commands,
     for i in 0..light_vec.len()
     {
         commands
-        .spawn(PointLightBundle {
+        .spawn((PointLightBundle {
             // transform: Transform::from_xyz(5.0, 8.0, 2.0),
             transform: Transform::from_xyz(light_vec[i].x,light_vec[i].z,-light_vec[i].y),
             point_light: PointLight {
@@ -198,7 +377,10 @@ fn setup_map(mut commands: Commands,
                 ..default()
             },
             ..default()
-        });
+        },
+        // lights only cast shadows from meshes on their own layers, so the gun needs them too
+        RenderLayers::from_layers(&[0, viewmodel::VIEWMODEL_LAYER]),
+        ));
     }
     let mut enemy_vec = Vec::new();
     enemy_vec.push(Vec3::new(38.,3.,0.02));
@@ -232,14 +414,13 @@ fn setup_map(mut commands: Commands,
         let mut person_transform = Transform::from_xyz(enemy_vec[(2*i)].x,enemy_vec[(2*i)].z,-enemy_vec[(2*i)].y);
         person_transform.scale = Vec3::new(2.5, 2.5, 2.5);
         person_transform.rotate_y(1.5708*enemy_vec[(2*i)+1].y);
-
-        println!("{}",enemy_vec[(2*i)+1].y);
         commands.spawn((
             SceneBundle {
                 transform: person_transform,
                 scene: asset_server.load("person.glb#Scene0"),
                 ..default()
             },
+            Name::new(format!("BOT {}", i + 1)),
             enemy::Enemy {
                 respawned : true,
                 respawn_timer : 0.,
@@ -247,6 +428,7 @@ fn setup_map(mut commands: Commands,
                 shoot_timer: 3.,
                 shoot_cooldown: 3.,
                 added_colliders: false,
+                last_hit: None,
             },
             NoFrustumCulling,
         ));
@@ -264,27 +446,9 @@ fn setup_map(mut commands: Commands,
 
     )).insert(x_shape);
     map_status.loaded = true;
+    Ok(())
 }
-fn setup_physics(
-    asset_server: Res<AssetServer>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut loading: ResMut<AssetsLoading>
-) {
-    let x_shape: Handle<Mesh> = asset_server.load("map.glb#Mesh0/Primitive0");
-    loading.0.push(x_shape.clone_untyped());
-}
-pub fn setup_ui(
-    asset_server: Res<AssetServer>,
-    mut commands: Commands,
-    primary_query: Query<&Window, With<PrimaryWindow>>,
-) {
-    let Ok(primary) = primary_query.get_single() else
-    {
-        return;
-    };
-
+pub fn setup_ui(mut commands: Commands) {
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -295,55 +459,20 @@ pub fn setup_ui(
             ..default()
         })
         .with_children(|parent| {
-            parent.spawn(ImageBundle {
-                image: UiImage {
-                    texture: asset_server.load("crosshair.png"),
-                    ..default()
-                },
-                style: Style {
-                    size: Size::new(Val::Px(9.), Val::Px(9.)),
-                    position: UiRect::new(
-                        Val::Px(primary.width() / 2. - 4.5),
-                        Val::Px(primary.width() / 2. + 4.5),
-                        Val::Px(primary.height() / 2. - 4.5),
-                        Val::Px(primary.height() / 2. + 4.5),
-                    ),
-                    ..default()
-                },
-                ..default()
-            });
-            parent
-                .spawn(NodeBundle {
+            crosshair::spawn_crosshair(parent);
+            parent.spawn((
+                NodeBundle {
                     style: Style {
-                        position: UiRect::new(
-                            Val::Percent(0.),
-                            Val::Percent(100.),
-                            Val::Px(primary.height() - 30.),
-                            Val::Px(primary.height() + 0.),
-                        ),
+                        size: Size::new(Val::Px(4.), Val::Px(4.)),
+                        position_type: PositionType::Absolute,
                         ..default()
                     },
-                    background_color: Color::rgba(0.15, 0.15, 0.15, 0.).into(),
+                    background_color: Color::rgb(1., 0.3, 0.3).into(),
+                    visibility: Visibility::Hidden,
                     ..default()
-                })
-                .with_children(|parent| {
-                    parent.spawn((
-                        gun_control::AmmoText {},
-                        TextBundle::from_section(
-                            "25/100",
-                            TextStyle {
-                                font: asset_server.load("font.ttf"),
-                                font_size: 30.0,
-                                color: Color::WHITE,
-                            },
-                        )
-                        .with_style(Style {
-                            margin: UiRect::all(Val::Px(5.0)),
-                            ..default()
-                        }),
-                        Label,
-                    ));
-                });
+                },
+                recoil::RecoilIndicator {},
+            ));
         });
 }
 #[derive(Resource)]
@@ -352,6 +481,7 @@ pub struct Animations(Vec<Handle<AnimationClip>>);
 #[derive(Resource)]
 pub struct EnemyAnimations(Vec<Handle<AnimationClip>>);
 pub fn setup(
+    settings: Res<settings::Settings>,
     asset_server: Res<AssetServer>,
     mut commands: Commands,
 ) {
@@ -365,7 +495,6 @@ pub fn setup(
         asset_server.load("person.glb#Animation0"),
         asset_server.load("person.glb#Animation1")
     ]));
-    WindowResolution::new(1980., 1080.);
     // ambient light
     commands.insert_resource(AmbientLight {
         color: Color::WHITE,
@@ -380,80 +509,144 @@ pub fn setup(
         //.spawn(TransformBundle::from(Transform::from_rotation(Quat::from_rotation_x(0.2),)))
         .with_children(|child| {
             child.spawn((
-                Visibility::Visible,
-                Camera3dBundle {
-                    camera: Camera {
-                        hdr: true, // 1. HDR is required for bloom
-                        ..default()
-                    },
-                    tonemapping: Tonemapping::TonyMcMapface,
-                    projection: Projection::Perspective(PerspectiveProjection {
-                        fov: (103.0 / 360.0) * (std::f32::consts::PI * 2.0),
-                        ..Default::default()
-                    }),
+                SpatialBundle {
+                    visibility: Visibility::Visible,
                     transform: Transform::from_xyz(0.0, 1.0, 4.0),
-
-                    ..default()
-                },
-                BloomSettings {
-                    intensity: 0.2,
                     ..default()
                 },
-                RigidBody::Dynamic,
-                LockedAxes::ROTATION_LOCKED_X
-                    | LockedAxes::ROTATION_LOCKED_Y
-                    | LockedAxes::ROTATION_LOCKED_Z,
-                Velocity {
-                    linvel: Vec3::new(0.0, 0.0, 0.0),
-                    angvel: Vec3::new(0.0, 0.0, 0.0),
-                },
-                Collider::cuboid(0.2, 1.4, 0.2),
+                RigidBody::KinematicPositionBased,
+                Collider::capsule_y(1.2, 0.2),
+                fps_movement::player_controller(1.2, 0.2),
                 fps_camera::FPSCamera {
-                    camera_shake_readjustment_factor: 0.3,
-                    recoil_shake: Vec3::ZERO,
+                    view_recoil: recoil::RecoilOffset::default(),
+                    aim_punch: recoil::RecoilOffset::default(),
                     rotation: Vec3::new(0., 0., 0.),
+                    look_speed: Vec2::ZERO,
                     speed: 300.,
                     rotate_lock: 88. * 0.0174533,
-                    sensitivity: (0.35) / 900.,
-                },
-                Damping {
-                    linear_damping: 4.,
-                    angular_damping: 1.0,
+                    sensitivity: settings.camera_sensitivity(),
+                    vertical_sensitivity: settings.camera_vertical_sensitivity(),
+                    ads_sensitivity_multiplier: 1.,
+                    aiming: false,
+                    pending_look: Vec2::ZERO,
+                    lean: 0.,
+                    lean_angle: 12_f32.to_radians(),
+                    lean_distance: 0.45,
+                    lean_speed: 5.,
                 },
-                GravityScale(1.),
                 fps_movement::FPSMovement {
                     speed: 2.2,
                     acceleration: 400.,
+                    velocity: Vec3::ZERO,
+                    grounded: false,
+                    ground_friction: 8.,
+                    jump_speed: 4.,
+                    model: fps_movement::MovementModel::Classic,
+                    state: fps_movement::MovementState::Walking,
+                    sprint_multiplier: 1.6,
+                    crouch_multiplier: 0.5,
+                    max_speed: 2.2,
+                    slide_boost: 1.3,
+                    slide_friction: 1.2,
+                    slide_time: 0.9,
+                    slide_timer: 0.,
+                    collider_radius: 0.2,
+                    stand_half_height: 1.2,
+                    crouch_half_height: 0.6,
+                    collider_half_height: 1.2,
+                    crouch_speed: 6.,
+                    stamina_settings: fps_movement::StaminaSettings::default(),
+                    stamina: 100.,
+                    stamina_regen_timer: 0.,
+                    exhausted: false,
+                    fall_damage: fps_movement::FallDamage::default(),
+                    climb_speed: 3.,
+                    ladder_detach_timer: 0.,
                 },
-            ));
+                player_health::PlayerHealth {
+                    health: 100.,
+                    max_health: 100.,
+                    armor: 0.,
+                    max_armor: 0.,
+                },
+                camera_effects::CameraEffects {
+                    enabled: settings.camera_effects,
+                    ..default()
+                },
+                third_person::ThirdPersonCamera {
+                    enabled: settings.third_person,
+                    distance: 3.,
+                    shoulder_offset: Vec3::new(0.6, 0.25, 0.),
+                    arm_radius: 0.2,
+                    return_speed: 6.,
+                    arm_length: 3.,
+                    muzzle: None,
+                },
+            ))
+            .with_children(|player| {
+                // the view sits on a child so leaning can move it without moving the collider
+                player.spawn((
+                    Camera3dBundle {
+                        camera: Camera {
+                            hdr: true, // 1. HDR is required for bloom
+                            ..default()
+                        },
+                        tonemapping: Tonemapping::TonyMcMapface,
+                        projection: Projection::Perspective(PerspectiveProjection {
+                            fov: settings.fov_radians(),
+                            ..Default::default()
+                        }),
+                        ..default()
+                    },
+                    BloomSettings {
+                        intensity: settings.bloom_intensity,
+                        ..default()
+                    },
+                    fps_camera::PlayerView {},
+                ))
+                .with_children(|view| {
+                    view.spawn((
+                        Camera3dBundle {
+                            camera: Camera {
+                                hdr: true,
+                                order: 1,
+                                ..default()
+                            },
+                            camera_3d: Camera3d {
+                                // keep the world underneath, but clear depth so walls can't
+                                // cover the gun
+                                clear_color: ClearColorConfig::None,
+                                depth_load_op: Camera3dDepthLoadOp::Clear(0.),
+                            },
+                            tonemapping: Tonemapping::TonyMcMapface,
+                            projection: Projection::Perspective(PerspectiveProjection {
+                                fov: settings.viewmodel_fov_radians(),
+                                near: 0.01,
+                                ..Default::default()
+                            }),
+                            ..default()
+                        },
+                        RenderLayers::layer(viewmodel::VIEWMODEL_LAYER),
+                        viewmodel::ViewmodelCamera {},
+                    ));
+                });
+            });
         });
-    let mut spray_pattern_primary = Vec::new();
-    spray_pattern_primary.push(Vec2::new(0., 0.));
-    spray_pattern_primary.push(Vec2::new(0., 0.007));
-    spray_pattern_primary.push(Vec2::new(0., 0.011));
-    spray_pattern_primary.push(Vec2::new(0.008, 0.019));
-    spray_pattern_primary.push(Vec2::new(-0.001, 0.032));
-    spray_pattern_primary.push(Vec2::new(-0.007, 0.042));
-    spray_pattern_primary.push(Vec2::new(-0.003, 0.07));
-    spray_pattern_primary.push(Vec2::new(0.0008, 0.09));
-    spray_pattern_primary.push(Vec2::new(0.01, 0.12));
-    spray_pattern_primary.push(Vec2::new(0.0068, 0.144));
-    spray_pattern_primary.push(Vec2::new(0.002, 0.158));
-    spray_pattern_primary.push(Vec2::new(0.01, 0.161));
-    spray_pattern_primary.push(Vec2::new(0.001, 0.179));
-    //random ish
-    spray_pattern_primary.push(Vec2::new(0.008, 0.2));
-    spray_pattern_primary.push(Vec2::new(0.018, 0.21));
-    spray_pattern_primary.push(Vec2::new(0.038, 0.19));
-    spray_pattern_primary.push(Vec2::new(0.04, 0.17));
-    spray_pattern_primary.push(Vec2::new(0.082, 0.2));
-    spray_pattern_primary.push(Vec2::new(0.11, 0.22));
-    spray_pattern_primary.push(Vec2::new(0.06, 0.2));
-    spray_pattern_primary.push(Vec2::new(0.04, 0.21));
-    spray_pattern_primary.push(Vec2::new(0., 0.18));
-    spray_pattern_primary.push(Vec2::new(-0.01, 0.206));
-    spray_pattern_primary.push(Vec2::new(-0.033, 0.19));
-    spray_pattern_primary.push(Vec2::new(-0.022, 0.2));
+    let mut player_model_transform = Transform::from_xyz(0., 0., 0.);
+    player_model_transform.scale = Vec3::new(2.5, 2.5, 2.5);
+    commands.spawn((
+        SceneBundle {
+            transform: player_model_transform,
+            scene: asset_server.load("person.glb#Scene0"),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        third_person::PlayerModel {
+            gun: None,
+            stride_phase: 0.,
+        },
+        NoFrustumCulling,
+    ));
     commands.spawn((
         SceneBundle {
             transform: Transform::from_xyz(0., 0., 0.),
@@ -461,24 +654,30 @@ pub fn setup(
             ..default()
         },
         gun_control::GunController {
+            definition: "rifle".to_string(),
             movement_inaccuracy: 0.,
+            lowered: false,
             reloading_time: 1.0,
             reloading_timer: 0.,
             spray_rand: 0.01,
             aiming_down_sights: false,
-            recoil_shake: Vec3::ZERO,
+            ads_fov: 70.,
+            gun_kick: recoil::RecoilOffset::default(),
             current_camera_transform: Transform::from_xyz(0.0, 0.0, 4.0),
-            smooth_scale: 0.6,
             magazine_size: 25,
             bullets: 25,
+            reserve_ammo: None,
+            fire_mode: gun_control::FireMode::Auto,
             spray_index: 0,
             recoil_reset_time: 0.32,
             time_since_last_shot: 0.,
             cooldown: 0.1,
             timer: 0.,
-            dynamic_offset: Vec3::ZERO,
-            target_offset: Vec3::ZERO,
-            spray_pattern: spray_pattern_primary,
+            sway: weapon_sway::WeaponSway::default(),
+            // filled in from the weapon file once the loading screen has checked it
+            recoil: recoil::RecoilSettings::default(),
+            sway_settings: weapon_sway::WeaponSwaySettings::default(),
+            spray_pattern: Vec::new(),
             shoot: false,
             gun_scale: 0.26,
             offset: Vec3::new(0., 0., 0.),
@@ -504,7 +703,6 @@ pub fn setup(
     
      */
     
-    let _rng = rand::thread_rng();
     /*
     let mut pos_vec = Vec::new();
     for i in 0..5 {
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    config_file::{data_file_path, save_toml},
+    game_mode::MatchStats,
+    kill_feed::KillEvent,
+    personal_best::unix_timestamp,
+};
+
+#[derive(Serialize, Clone, Debug)]
+pub struct KillRecord {
+    // seconds into the match
+    pub time: f32,
+    pub killer: String,
+    pub victim: String,
+    pub weapon: String,
+    pub headshot: bool,
+    pub distance: f32,
+    pub position: [f32; 3],
+}
+
+// everything that happened in one match, written out when it ends for looking over later
+#[derive(Resource, Serialize, Default)]
+pub struct MatchLog {
+    pub mode: String,
+    pub scenario: String,
+    pub map: String,
+    // toml integers are signed, so the full u64 range doesn't fit in one
+    pub seed: String,
+    pub started: u64,
+    pub duration: f32,
+    pub score: i32,
+    pub accuracy: f32,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    pub kills: Vec<KillRecord>,
+}
+
+pub fn start_match_log(match_stats: Res<MatchStats>, mut match_log: ResMut<MatchLog>) {
+    *match_log = MatchLog {
+        mode: match_stats.mode.name().to_string(),
+        scenario: match_stats.scenario.clone(),
+        map: match_stats.map.clone(),
+        seed: match_stats.seed.to_string(),
+        started: unix_timestamp(),
+        duration: match_stats.duration,
+        ..default()
+    };
+}
+
+pub fn record_kills(
+    match_stats: Res<MatchStats>,
+    mut match_log: ResMut<MatchLog>,
+    mut kill_events: EventReader<KillEvent>,
+) {
+    for kill in kill_events.iter() {
+        match_log.kills.push(KillRecord {
+            time: match_stats.elapsed,
+            killer: kill.killer.clone(),
+            victim: kill.victim.clone(),
+            weapon: kill.weapon.clone(),
+            headshot: kill.headshot,
+            distance: kill.distance,
+            position: kill.position.to_array(),
+        });
+    }
+}
+
+// one file per match, named so they sort by when they were played
+pub fn save_match_log(match_stats: Res<MatchStats>, mut match_log: ResMut<MatchLog>) {
+    match_log.score = match_stats.score();
+    match_log.accuracy = match_stats.accuracy();
+    match_log.shots_fired = match_stats.shots_fired;
+    match_log.shots_hit = match_stats.shots_hit;
+    let file_name = format!("matches/{}-{}.toml", match_log.started, match_log.mode);
+    match data_file_path(&file_name) {
+        Some(path) => save_toml(&path, &*match_log),
+        None => warn!("no platform data directory, the match log will not be saved"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_log_writes_as_toml() {
+        let match_log = MatchLog {
+            mode: "waves".to_string(),
+            seed: u64::MAX.to_string(),
+            kills: vec![KillRecord {
+                time: 12.5,
+                killer: "PLAYER".to_string(),
+                victim: "BOT 1".to_string(),
+                weapon: "rifle".to_string(),
+                headshot: true,
+                distance: 20.,
+                position: [1., 0., -3.],
+            }],
+            ..default()
+        };
+        let contents = toml::to_string_pretty(&match_log).unwrap();
+        assert!(contents.contains("[[kills]]"));
+        assert!(contents.contains("victim = \"BOT 1\""));
+    }
+}
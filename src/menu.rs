@@ -0,0 +1,186 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    controls_menu::ControlsMenu,
+    game_mode::{GameMode, MatchStats},
+    game_state::AppState,
+    settings::{Settings, SettingsMenu},
+};
+
+const BUTTON_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVER_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+
+#[derive(Component)]
+pub struct MainMenuScreen {}
+
+#[derive(Component)]
+pub struct PauseScreen {}
+
+#[derive(Component, Clone, Copy)]
+pub enum MenuButton {
+    Play(GameMode),
+    PlayAgain,
+    Resume,
+    Settings,
+    CloseSettings,
+    Controls,
+    CloseControls,
+    MainMenu,
+    Quit,
+}
+
+pub fn new_match(mode: GameMode) -> MatchStats {
+    match mode {
+        GameMode::AimTrainer => MatchStats::new(mode, "enemies", "map", 60.),
+        GameMode::Waves => MatchStats::new(mode, "enemies", "map", 180.),
+        GameMode::Movement => MatchStats::new(mode, "movement", "map", 120.),
+    }
+}
+
+pub fn spawn_menu_screen<T: Component>(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    marker: T,
+    title: &str,
+    buttons: &[(&str, MenuButton)],
+) {
+    let font = asset_server.load("font.ttf");
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                ..default()
+            },
+            marker,
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    title,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 60.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+            for (label, button) in buttons {
+                spawn_button(parent, font.clone(), label, *button);
+            }
+        });
+}
+
+pub fn spawn_button(parent: &mut ChildBuilder, font: Handle<Font>, label: &str, button: MenuButton) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                    margin: UiRect::all(Val::Px(8.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font,
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+}
+
+pub fn setup_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu_screen(
+        &mut commands,
+        &asset_server,
+        MainMenuScreen {},
+        "FPS GAME",
+        &[
+            ("AIM TRAINER", MenuButton::Play(GameMode::AimTrainer)),
+            ("WAVES", MenuButton::Play(GameMode::Waves)),
+            ("MOVEMENT", MenuButton::Play(GameMode::Movement)),
+            ("SETTINGS", MenuButton::Settings),
+            ("QUIT", MenuButton::Quit),
+        ],
+    );
+}
+
+pub fn setup_pause_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu_screen(
+        &mut commands,
+        &asset_server,
+        PauseScreen {},
+        "PAUSED",
+        &[
+            ("RESUME", MenuButton::Resume),
+            ("SETTINGS", MenuButton::Settings),
+            ("MAIN MENU", MenuButton::MainMenu),
+            ("QUIT", MenuButton::Quit),
+        ],
+    );
+}
+
+pub fn handle_menu_buttons(
+    mut interaction_query: Query<
+        (&Interaction, &MenuButton, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut match_stats: ResMut<MatchStats>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit_writer: EventWriter<AppExit>,
+    mut settings_menu: ResMut<SettingsMenu>,
+    mut controls_menu: ResMut<ControlsMenu>,
+    settings: Res<Settings>,
+) {
+    for (interaction, button, mut background_color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => match button {
+                MenuButton::Play(mode) => {
+                    *match_stats = new_match(*mode);
+                    next_state.set(AppState::InGame);
+                }
+                MenuButton::PlayAgain => {
+                    *match_stats = new_match(match_stats.mode);
+                    next_state.set(AppState::InGame);
+                }
+                MenuButton::Resume => next_state.set(AppState::InGame),
+                MenuButton::Settings => settings_menu.open = true,
+                MenuButton::CloseSettings => {
+                    settings_menu.open = false;
+                    settings.save();
+                }
+                MenuButton::Controls => controls_menu.open = true,
+                MenuButton::CloseControls => {
+                    controls_menu.open = false;
+                    controls_menu.waiting_for = None;
+                    settings.save();
+                }
+                MenuButton::MainMenu => next_state.set(AppState::MainMenu),
+                MenuButton::Quit => exit_writer.send(AppExit),
+            },
+            Interaction::Hovered => *background_color = BUTTON_HOVER_COLOR.into(),
+            Interaction::None => *background_color = BUTTON_COLOR.into(),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+
+pub struct MouseLookOptions {
+    // seconds for pending motion to catch up, 0 applies everything the frame it arrives
+    pub smoothing: f32,
+    // extra sensitivity per 1000 counts per second of mouse speed, 0 is off
+    pub acceleration: f32,
+    pub acceleration_cap: f32,
+}
+
+pub fn accumulate_motion<'a>(deltas: impl Iterator<Item = &'a Vec2>) -> Vec2 {
+    deltas.fold(Vec2::ZERO, |total, delta| total + *delta)
+}
+
+pub fn acceleration_multiplier(raw: Vec2, delta_seconds: f32, options: &MouseLookOptions) -> f32 {
+    if options.acceleration <= 0. || delta_seconds <= 0. {
+        return 1.;
+    }
+    let counts_per_second = raw.length() / delta_seconds;
+    f32::min(
+        1. + options.acceleration * counts_per_second / 1000.,
+        options.acceleration_cap.max(1.),
+    )
+}
+
+// moves raw motion into the pending buffer and returns how much of it to apply this frame,
+// nothing is dropped: whatever smoothing holds back is applied on later frames
+pub fn take_look_delta(
+    pending: &mut Vec2,
+    raw: Vec2,
+    delta_seconds: f32,
+    options: &MouseLookOptions,
+) -> Vec2 {
+    *pending += raw * acceleration_multiplier(raw, delta_seconds, options);
+    if options.smoothing <= 0. {
+        let applied = *pending;
+        *pending = Vec2::ZERO;
+        return applied;
+    }
+    let blend = 1. - f32::exp(-delta_seconds / options.smoothing);
+    let applied = *pending * blend;
+    *pending -= applied;
+    applied
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config_file::{data_file_path, load_toml, save_toml},
+    fps_movement::FPSMovement,
+    game_mode::MatchStats,
+    settings::Settings,
+};
+
+const SAVE_FILE_NAME: &str = "personal_bests.toml";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordedSettings {
+    pub sensitivity: f32,
+    pub fov: f32,
+    pub movement_speed: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PersonalBestRecord {
+    pub score: i32,
+    pub accuracy: f32,
+    pub kills: u32,
+    pub headshots: u32,
+    pub timestamp: u64,
+    pub settings: RecordedSettings,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct PersonalBestFile {
+    #[serde(default)]
+    pub records: BTreeMap<String, PersonalBestRecord>,
+}
+
+#[derive(Resource)]
+pub struct PersonalBests {
+    pub path: Option<PathBuf>,
+    pub file: PersonalBestFile,
+}
+
+pub struct MatchResult {
+    pub record: PersonalBestRecord,
+    pub previous_best: Option<PersonalBestRecord>,
+    pub new_best: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct LastMatchResult(pub Option<MatchResult>);
+
+pub fn record_key(mode: &str, scenario: &str, map: &str) -> String {
+    format!("{}/{}/{}", mode, scenario, map)
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+impl PersonalBests {
+    pub fn load() -> Self {
+        let path = data_file_path(SAVE_FILE_NAME);
+        let file = match path.as_ref() {
+            Some(file_path) => load_toml(file_path),
+            None => {
+                warn!("no platform data directory, personal bests will not be saved");
+                PersonalBestFile::default()
+            }
+        };
+        PersonalBests { path, file }
+    }
+
+    pub fn save(&self) {
+        if let Some(file_path) = self.path.as_ref() {
+            save_toml(file_path, &self.file);
+        }
+    }
+
+    pub fn submit(&mut self, key: String, record: PersonalBestRecord) -> MatchResult {
+        let previous_best = self.file.records.get(&key).cloned();
+        let new_best = match &previous_best {
+            Some(best) => record.score > best.score,
+            None => true,
+        };
+        if new_best {
+            self.file.records.insert(key, record.clone());
+            self.save();
+        }
+        MatchResult {
+            record,
+            previous_best,
+            new_best,
+        }
+    }
+}
+
+pub fn record_personal_best(
+    match_stats: Res<MatchStats>,
+    mut personal_bests: ResMut<PersonalBests>,
+    mut last_result: ResMut<LastMatchResult>,
+    settings: Res<Settings>,
+    movement_query: Query<&FPSMovement>,
+) {
+    let Ok(movement) = movement_query.get_single() else {
+        return;
+    };
+    let record = PersonalBestRecord {
+        score: match_stats.score(),
+        accuracy: match_stats.accuracy(),
+        kills: match_stats.kills,
+        headshots: match_stats.headshots,
+        timestamp: unix_timestamp(),
+        settings: RecordedSettings {
+            sensitivity: settings.sensitivity,
+            fov: settings.fov,
+            movement_speed: movement.speed,
+        },
+    };
+    let key = record_key(
+        match_stats.mode.name(),
+        &match_stats.scenario,
+        &match_stats.map,
+    );
+    last_result.0 = Some(personal_bests.submit(key, record));
+}
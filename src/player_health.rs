@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera_effects::CameraTrauma,
+    fps_movement::{FPSMovement, PlayerLanded},
+    game_state::PLAYER_SPAWN,
+};
+
+#[derive(Component)]
+pub struct PlayerHealth {
+    pub health: f32,
+    pub max_health: f32,
+    // soaks up damage before health, only some modes hand it out
+    pub armor: f32,
+    pub max_armor: f32,
+}
+
+impl PlayerHealth {
+    pub fn take_damage(&mut self, damage: f32) {
+        let absorbed = f32::min(self.armor, damage);
+        self.armor -= absorbed;
+        self.health -= damage - absorbed;
+    }
+}
+
+#[derive(Component)]
+pub struct HealthText {}
+
+pub fn apply_fall_damage(
+    mut landed_events: EventReader<PlayerLanded>,
+    mut trauma_events: EventWriter<CameraTrauma>,
+    mut player_query: Query<(&mut PlayerHealth, &mut Transform, &mut FPSMovement)>,
+) {
+    for landed in landed_events.iter() {
+        if landed.damage <= 0. {
+            continue;
+        }
+        trauma_events.send(CameraTrauma(landed.damage / 50.));
+        for (mut player_health, mut transform, mut movement) in player_query.iter_mut() {
+            player_health.take_damage(landed.damage);
+            if player_health.health <= 0. {
+                // there is nothing to lose in the trainer modes, so a fatal fall just respawns
+                player_health.health = player_health.max_health;
+                transform.translation = PLAYER_SPAWN;
+                movement.velocity = Vec3::ZERO;
+            }
+        }
+    }
+}
+
+pub fn update_health_text(
+    player_query: Query<&PlayerHealth>,
+    mut text_query: Query<&mut Text, With<HealthText>>,
+) {
+    let Ok(player_health) = player_query.get_single() else {
+        return;
+    };
+    // checked every frame so a rebuilt hud fills in straight away
+    let value = format!("HP {:.0}", player_health.health.max(0.).ceil());
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+// a fixed algorithm, unlike StdRng which rand may swap out in any release and break every
+// saved seed with it
+pub type StreamRng = ChaCha8Rng;
+
+// each system draws from its own stream, so a change in how often one of them rolls can't
+// shift the numbers another one gets
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RandomStream {
+    Spread,
+    // the gun model's roll on each shot, only for show
+    GunKick,
+    Targets,
+}
+
+pub const ALL_STREAMS: [RandomStream; 3] = [
+    RandomStream::Spread,
+    RandomStream::GunKick,
+    RandomStream::Targets,
+];
+
+// all gameplay randomness comes from here, reseeded at the start of every match so a seed
+// replays the same spread and spawns
+#[derive(Resource)]
+pub struct GameRng {
+    pub seed: u64,
+    streams: Vec<StreamRng>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = GameRng {
+            seed,
+            streams: Vec::new(),
+        };
+        rng.reseed(seed);
+        rng
+    }
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        // seed_from_u64 scrambles its input, so neighbouring values still give unrelated streams
+        self.streams = (0..ALL_STREAMS.len() as u64)
+            .map(|index| StreamRng::seed_from_u64(seed.wrapping_add(index)))
+            .collect();
+    }
+    pub fn stream(&mut self, stream: RandomStream) -> &mut StreamRng {
+        let index = ALL_STREAMS
+            .iter()
+            .position(|s| *s == stream)
+            .unwrap_or_default();
+        &mut self.streams[index]
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(rand::random())
+    }
+}
+
+// FPS_GAME_SEED replays any match, otherwise the aim trainer always plays the same run so
+// scores are comparable and the other modes are different every time
+pub fn match_seed(fixed: bool, name: &str) -> u64 {
+    if let Some(seed) = std::env::var("FPS_GAME_SEED")
+        .ok()
+        .and_then(|seed| seed.trim().parse().ok())
+    {
+        return seed;
+    }
+    if fixed {
+        seed_from_name(name)
+    } else {
+        rand::random()
+    }
+}
+
+// the same name always gives the same seed, on every platform and build
+pub fn seed_from_name(name: &str) -> u64 {
+    // fnv-1a
+    name.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn rolls(rng: &mut GameRng, stream: RandomStream) -> Vec<u32> {
+        (0..8).map(|_| rng.stream(stream).gen()).collect()
+    }
+
+    #[test]
+    fn same_seed_replays_the_same_numbers() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(7);
+        b.reseed(42);
+        assert_eq!(rolls(&mut a, RandomStream::Spread), rolls(&mut b, RandomStream::Spread));
+    }
+
+    #[test]
+    fn seeds_give_the_same_numbers_on_every_build() {
+        let spread = rolls(&mut GameRng::new(42), RandomStream::Spread);
+        assert_eq!(spread[..2], [962419617, 2928721845]);
+    }
+
+    #[test]
+    fn streams_do_not_affect_each_other() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(42);
+        rolls(&mut b, RandomStream::Spread);
+        assert_eq!(rolls(&mut a, RandomStream::Targets), rolls(&mut b, RandomStream::Targets));
+        assert_ne!(rolls(&mut a, RandomStream::Spread), rolls(&mut a, RandomStream::GunKick));
+    }
+}
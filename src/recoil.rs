@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fps_camera::{FPSCamera, PlayerView},
+    gun_control::GunController,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RecoveryCurve {
+    Linear,
+    // fast at first and settling gently
+    EaseOut,
+    // slow to start, then snapping back
+    EaseIn,
+}
+
+impl RecoveryCurve {
+    // how much of the kick is left once `progress` of the recovery time has passed
+    pub fn remaining(&self, progress: f32) -> f32 {
+        let t = progress.clamp(0., 1.);
+        match self {
+            RecoveryCurve::Linear => 1. - t,
+            RecoveryCurve::EaseOut => (1. - t) * (1. - t),
+            RecoveryCurve::EaseIn => 1. - t * t,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RecoilRecovery {
+    // seconds after the last kick before recovery starts
+    pub delay: f32,
+    pub time: f32,
+    pub curve: RecoveryCurve,
+}
+
+// nan fails every comparison, so checking for what is allowed catches it too
+fn check_non_negative(name: &str, value: f32) -> Result<(), String> {
+    if value >= 0. && value.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("recoil {} is {}, it has to be zero or more", name, value))
+    }
+}
+
+impl RecoilRecovery {
+    fn validate(&self, name: &str) -> Result<(), String> {
+        check_non_negative(&format!("{}.delay", name), self.delay)?;
+        check_non_negative(&format!("{}.time", name), self.time)
+    }
+}
+
+// an offset that jumps on every shot and eases back to zero. for the view and aim punch x is
+// pitch and y is yaw in radians, for the gun x is pitch, y is meters pushed back and z is roll
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RecoilOffset {
+    pub amount: Vec3,
+    pub start: Vec3,
+    pub elapsed: f32,
+}
+
+impl RecoilOffset {
+    pub fn kick(&mut self, amount: Vec3, recovery: &RecoilRecovery) {
+        self.amount += amount;
+        self.start = self.amount;
+        self.elapsed = -recovery.delay;
+    }
+    pub fn recover(&mut self, recovery: &RecoilRecovery, delta_seconds: f32) {
+        self.elapsed += delta_seconds;
+        if self.elapsed <= 0. {
+            return;
+        }
+        let progress = if recovery.time > 0. {
+            self.elapsed / recovery.time
+        } else {
+            1.
+        };
+        self.amount = self.start * recovery.curve.remaining(progress);
+    }
+    pub fn reset(&mut self) {
+        *self = RecoilOffset::default();
+    }
+}
+
+// per weapon. each step of the spray pattern is split between moving the view and punching
+// the aim away from it, the gun model kick is only for show
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecoilSettings {
+    pub view_kick: f32,
+    pub aim_punch: f32,
+    // pitch in radians, meters pushed back and the most roll in radians, per shot
+    pub gun_kick: Vec3,
+    pub view_recovery: RecoilRecovery,
+    pub punch_recovery: RecoilRecovery,
+    pub gun_recovery: RecoilRecovery,
+}
+
+impl Default for RecoilSettings {
+    fn default() -> Self {
+        RecoilSettings {
+            view_kick: 0.7,
+            aim_punch: 0.3,
+            gun_kick: Vec3::new(0.05, 0.04, 0.03),
+            view_recovery: RecoilRecovery {
+                delay: 0.12,
+                time: 0.35,
+                curve: RecoveryCurve::EaseOut,
+            },
+            punch_recovery: RecoilRecovery {
+                delay: 0.12,
+                time: 0.25,
+                curve: RecoveryCurve::EaseOut,
+            },
+            gun_recovery: RecoilRecovery {
+                delay: 0.,
+                time: 0.09,
+                curve: RecoveryCurve::EaseOut,
+            },
+        }
+    }
+}
+
+impl RecoilSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        check_non_negative("view_kick", self.view_kick)?;
+        check_non_negative("aim_punch", self.aim_punch)?;
+        check_non_negative("gun_kick pitch", self.gun_kick.x)?;
+        check_non_negative("gun_kick push", self.gun_kick.y)?;
+        check_non_negative("gun_kick roll", self.gun_kick.z)?;
+        self.view_recovery.validate("view_recovery")?;
+        self.punch_recovery.validate("punch_recovery")?;
+        self.gun_recovery.validate("gun_recovery")
+    }
+}
+
+// the pattern stores where each shot lands relative to the first, x to the right and y up,
+// so a shot kicks by how far it is from the one before
+pub fn pattern_step(spray_pattern: &[Vec2], spray_index: usize) -> Vec2 {
+    if spray_index == 0 {
+        return Vec2::ZERO;
+    }
+    let point = |index: usize| spray_pattern.get(index).copied().unwrap_or_default();
+    let current = point(spray_index - 1);
+    if spray_index == 1 {
+        current
+    } else {
+        current - point(spray_index - 2)
+    }
+}
+
+pub fn apply_recoil_kick(
+    gun_controller: &mut GunController,
+    camera: &mut FPSCamera,
+    random_roll: f32,
+) {
+    let step = pattern_step(&gun_controller.spray_pattern, gun_controller.spray_index);
+    // right in the pattern turns the view clockwise, which is negative yaw
+    let kick = Vec3::new(step.y, -step.x, 0.);
+    let recoil = &gun_controller.recoil;
+    camera.view_recoil.kick(kick * recoil.view_kick, &recoil.view_recovery);
+    camera.aim_punch.kick(kick * recoil.aim_punch, &recoil.punch_recovery);
+    let gun_kick = recoil.gun_kick * Vec3::new(1., 1., random_roll);
+    gun_controller.gun_kick.kick(gun_kick, &recoil.gun_recovery);
+}
+
+// pitch and yaw the next bullet goes at before spread, the view plus whatever punch is left
+pub fn aim_angles(camera: &FPSCamera) -> Vec2 {
+    let recoil = camera.view_recoil.amount + camera.aim_punch.amount;
+    Vec2::new(camera.rotation.x + recoil.x, camera.rotation.y + recoil.y)
+}
+
+pub fn aim_rotation(camera: &FPSCamera) -> Quat {
+    let angles = aim_angles(camera);
+    Quat::from_rotation_y(angles.y) * Quat::from_rotation_x(angles.x)
+}
+
+pub fn update_recoil(
+    time: Res<Time>,
+    mut gun_query: Query<&mut GunController>,
+    mut camera_query: Query<&mut FPSCamera>,
+) {
+    let delta_seconds = time.delta_seconds();
+    let Ok(mut gun_controller) = gun_query.get_single_mut() else {
+        return;
+    };
+    let gun_controller = gun_controller.as_mut();
+    let recoil = &gun_controller.recoil;
+    gun_controller.gun_kick.recover(&recoil.gun_recovery, delta_seconds);
+    for mut camera in camera_query.iter_mut() {
+        camera.view_recoil.recover(&recoil.view_recovery, delta_seconds);
+        camera.aim_punch.recover(&recoil.punch_recovery, delta_seconds);
+    }
+}
+
+#[derive(Component)]
+pub struct RecoilIndicator {}
+
+// marks where the next bullet goes while aim punch or a camera effect pulls it off the
+// crosshair
+pub fn update_recoil_indicator(
+    ui_scale: Res<UiScale>,
+    camera_query: Query<&FPSCamera>,
+    view_query: Query<(&Camera, &GlobalTransform), With<PlayerView>>,
+    mut indicator_query: Query<(&mut Style, &mut Visibility), With<RecoilIndicator>>,
+) {
+    let (Ok(camera), Ok((view_camera, view_transform))) =
+        (camera_query.get_single(), view_query.get_single())
+    else {
+        return;
+    };
+    let aim_direction = aim_rotation(camera) * Vec3::NEG_Z;
+    let target = view_transform.translation() + aim_direction * 100.;
+    let screen_position = view_camera.world_to_viewport(view_transform, target);
+    let centre = view_camera.logical_viewport_size().map(|size| size / 2.);
+    for (mut style, mut visibility) in indicator_query.iter_mut() {
+        match (screen_position, centre) {
+            (Some(position), Some(centre)) if position.distance(centre) > 1.5 => {
+                let half_size = 2.;
+                // the viewport is in screen pixels and the ui in scaled ones
+                let position = position / ui_scale.scale as f32;
+                style.position.left = Val::Px(position.x - half_size);
+                style.position.bottom = Val::Px(position.y - half_size);
+                *visibility = Visibility::Inherited;
+            }
+            _ => *visibility = Visibility::Hidden,
+        }
+    }
+}
@@ -0,0 +1,547 @@
+use bevy::{pbr::NotShadowCaster, prelude::*, window::PrimaryWindow};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    fps_camera::PlayerView,
+    fps_shooting::ShotFired,
+    gun_control::GunController,
+    input_actions::{Action, InputActions},
+    lock_cursor::CursorLockState,
+    settings::Settings,
+    weapon_definition::{save_spray_pattern, weapon_definition_path},
+};
+
+// the furthest the pattern is drawn from the player, it comes closer if a wall is in the way
+const WALL_DISTANCE: f32 = 10.;
+// pixels the cursor can be from a point and still pick it up
+const PICK_RADIUS: f32 = 12.;
+// marker radius per meter of wall distance, so they look the same size wherever the wall is
+const MARKER_SIZE: f32 = 0.006;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PatternEditorMode {
+    // drag the points of the current pattern around
+    Edit,
+    // spray and pull the mouse, the shots become the new pattern
+    Record,
+    // spray normally with the actual hits drawn over the pattern
+    Practice,
+}
+
+// a flat surface facing where a spray started. pattern offsets are angles, so they're turned
+// into distances along it to match what the player sees down the sights
+#[derive(Clone, Copy, Debug)]
+pub struct PatternWall {
+    pub origin: Vec3,
+    pub normal: Vec3,
+    pub right: Vec3,
+    pub up: Vec3,
+    pub distance: f32,
+}
+
+impl PatternWall {
+    pub fn facing(eye: Vec3, rotation: Quat, distance: f32) -> Self {
+        let forward = rotation * Vec3::NEG_Z;
+        PatternWall {
+            origin: eye + forward * distance,
+            normal: -forward,
+            right: rotation * Vec3::X,
+            up: rotation * Vec3::Y,
+            distance,
+        }
+    }
+    pub fn point(&self, offset: Vec2) -> Vec3 {
+        self.origin
+            + self.right * offset.x.tan() * self.distance
+            + self.up * offset.y.tan() * self.distance
+    }
+    pub fn offset(&self, point: Vec3) -> Vec2 {
+        let local = point - self.origin;
+        Vec2::new(
+            (local.dot(self.right) / self.distance).atan(),
+            (local.dot(self.up) / self.distance).atan(),
+        )
+    }
+    pub fn intersect(&self, origin: Vec3, direction: Vec3) -> Option<Vec3> {
+        let facing = direction.dot(self.normal);
+        if facing.abs() < 0.0001 {
+            return None;
+        }
+        let t = (self.origin - origin).dot(self.normal) / facing;
+        (t > 0.).then(|| origin + direction * t)
+    }
+}
+
+pub struct MarkerAssets {
+    pub mesh: Handle<Mesh>,
+    pub pattern: Handle<StandardMaterial>,
+    pub selected: Handle<StandardMaterial>,
+    pub shot: Handle<StandardMaterial>,
+}
+
+#[derive(Resource)]
+pub struct RecoilEditor {
+    pub open: bool,
+    pub mode: PatternEditorMode,
+    // the pattern being worked on, copied onto the gun as it changes
+    pub pattern: Vec<Vec2>,
+    pub recording: Vec<Vec2>,
+    // pitch and yaw the recorded spray started at
+    pub record_start: Vec2,
+    pub practice_shots: Vec<Vec3>,
+    pub wall: Option<PatternWall>,
+    pub dragging: Option<usize>,
+    pub markers_dirty: bool,
+    pub marker_assets: Option<MarkerAssets>,
+    pub status: String,
+}
+
+impl Default for RecoilEditor {
+    fn default() -> Self {
+        RecoilEditor {
+            open: false,
+            mode: PatternEditorMode::Edit,
+            pattern: Vec::new(),
+            recording: Vec::new(),
+            record_start: Vec2::ZERO,
+            practice_shots: Vec::new(),
+            wall: None,
+            dragging: None,
+            markers_dirty: false,
+            marker_assets: None,
+            status: String::new(),
+        }
+    }
+}
+
+impl RecoilEditor {
+    // the points drawn as the pattern in the current mode
+    pub fn shown_pattern(&self) -> &[Vec2] {
+        if self.mode == PatternEditorMode::Record {
+            &self.recording
+        } else {
+            &self.pattern
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PatternMarker {}
+
+#[derive(Component)]
+pub struct PatternEditorText {}
+
+pub fn not_editing_pattern(editor: Res<RecoilEditor>) -> bool {
+    !(editor.open && editor.mode == PatternEditorMode::Edit)
+}
+
+fn rotation_from_aim(aim: Vec2) -> Quat {
+    Quat::from_rotation_y(aim.y) * Quat::from_rotation_x(aim.x)
+}
+
+// a wall straight ahead of the view, pulled in front of anything solid so it isn't hidden
+fn wall_in_front(rapier_context: &RapierContext, view: &GlobalTransform) -> PatternWall {
+    let (_, rotation, eye) = view.to_scale_rotation_translation();
+    let distance = match rapier_context.cast_ray(
+        eye,
+        rotation * Vec3::NEG_Z,
+        WALL_DISTANCE,
+        true,
+        QueryFilter::only_fixed().exclude_sensors(),
+    ) {
+        Some((_, toi)) => f32::max(toi - 0.1, 1.),
+        None => WALL_DISTANCE,
+    };
+    PatternWall::facing(eye, rotation, distance)
+}
+
+fn leave_mode(
+    editor: &mut RecoilEditor,
+    gun_controller: &mut GunController,
+    cursor_lock_state: &mut CursorLockState,
+) {
+    match editor.mode {
+        PatternEditorMode::Edit => {
+            editor.dragging = None;
+            cursor_lock_state.allow_lock = true;
+        }
+        PatternEditorMode::Record => {
+            // leaving without firing keeps the old pattern
+            if !editor.recording.is_empty() {
+                editor.pattern = std::mem::take(&mut editor.recording);
+            }
+            gun_controller.spray_pattern = editor.pattern.clone();
+        }
+        PatternEditorMode::Practice => editor.practice_shots.clear(),
+    }
+    editor.wall = None;
+    editor.markers_dirty = true;
+}
+
+fn enter_mode(
+    editor: &mut RecoilEditor,
+    mode: PatternEditorMode,
+    gun_controller: &mut GunController,
+    cursor_lock_state: &mut CursorLockState,
+    wall: PatternWall,
+) {
+    editor.mode = mode;
+    editor.status.clear();
+    editor.markers_dirty = true;
+    match mode {
+        PatternEditorMode::Edit => {
+            editor.wall = Some(wall);
+            // the cursor is needed to drag points, so firing is off until another mode
+            cursor_lock_state.state = false;
+            cursor_lock_state.allow_lock = false;
+            gun_controller.shoot = false;
+        }
+        PatternEditorMode::Record => {
+            editor.wall = Some(wall);
+            editor.recording.clear();
+            // no kick while recording, the mouse movement is the pattern
+            gun_controller.spray_pattern.clear();
+        }
+        PatternEditorMode::Practice => {
+            // placed by the first shot of each spray
+            editor.wall = None;
+            editor.practice_shots.clear();
+        }
+    }
+}
+
+pub fn toggle_recoil_editor(
+    actions: Res<InputActions>,
+    rapier_context: Res<RapierContext>,
+    mut editor: ResMut<RecoilEditor>,
+    mut cursor_lock_state: ResMut<CursorLockState>,
+    mut gun_query: Query<&mut GunController>,
+    view_query: Query<&GlobalTransform, With<PlayerView>>,
+) {
+    let editor_actions = [
+        Action::ToggleRecoilEditor,
+        Action::EditPattern,
+        Action::RecordPattern,
+        Action::PracticePattern,
+        Action::SavePattern,
+    ];
+    if !editor_actions.iter().any(|action| actions.just_pressed(*action)) {
+        return;
+    }
+    let (Ok(mut gun_controller), Ok(view)) = (gun_query.get_single_mut(), view_query.get_single())
+    else {
+        return;
+    };
+    let editor = editor.as_mut();
+    let gun_controller = gun_controller.as_mut();
+    let cursor_lock_state = cursor_lock_state.as_mut();
+
+    if actions.just_pressed(Action::ToggleRecoilEditor) {
+        if editor.open {
+            leave_mode(editor, gun_controller, cursor_lock_state);
+            editor.open = false;
+        } else {
+            editor.open = true;
+            editor.pattern = gun_controller.spray_pattern.clone();
+            let wall = wall_in_front(&rapier_context, view);
+            enter_mode(
+                editor,
+                PatternEditorMode::Edit,
+                gun_controller,
+                cursor_lock_state,
+                wall,
+            );
+        }
+        return;
+    }
+    if !editor.open {
+        return;
+    }
+
+    let mode = if actions.just_pressed(Action::EditPattern) {
+        Some(PatternEditorMode::Edit)
+    } else if actions.just_pressed(Action::RecordPattern) {
+        Some(PatternEditorMode::Record)
+    } else if actions.just_pressed(Action::PracticePattern) {
+        Some(PatternEditorMode::Practice)
+    } else {
+        None
+    };
+    if let Some(mode) = mode {
+        leave_mode(editor, gun_controller, cursor_lock_state);
+        let wall = wall_in_front(&rapier_context, view);
+        enter_mode(editor, mode, gun_controller, cursor_lock_state, wall);
+    }
+
+    if actions.just_pressed(Action::SavePattern) {
+        let pattern = if editor.mode == PatternEditorMode::Record && !editor.recording.is_empty()
+        {
+            &editor.recording
+        } else {
+            &editor.pattern
+        };
+        save_spray_pattern(&gun_controller.definition, pattern);
+        editor.status = format!(
+            "saved {} points to {:?}",
+            pattern.len(),
+            weapon_definition_path(&gun_controller.definition)
+        );
+    }
+}
+
+fn record_shot(editor: &mut RecoilEditor, shot: &ShotFired) {
+    if shot.spray_index <= 1 || editor.recording.is_empty() {
+        editor.recording.clear();
+        editor.record_start = shot.aim;
+        let distance = editor.wall.map_or(WALL_DISTANCE, |wall| wall.distance);
+        editor.wall = Some(PatternWall::facing(
+            shot.origin,
+            rotation_from_aim(shot.aim),
+            distance,
+        ));
+    }
+    let turned = shot.aim - editor.record_start;
+    // turning right is negative yaw, but right is positive in the pattern
+    editor.recording.push(Vec2::new(-turned.y, turned.x));
+    editor.status = format!("recorded {} shots", editor.recording.len());
+}
+
+fn practice_shot(editor: &mut RecoilEditor, shot: &ShotFired) {
+    if shot.spray_index <= 1 || editor.wall.is_none() {
+        editor.practice_shots.clear();
+        // just in front of whatever the first shot hit, so the markers aren't buried in it
+        let distance = shot.hit.map_or(WALL_DISTANCE, |(_, point, _)| {
+            f32::max(point.distance(shot.origin) - 0.05, 1.)
+        });
+        editor.wall = Some(PatternWall::facing(
+            shot.origin,
+            rotation_from_aim(shot.aim),
+            distance,
+        ));
+    }
+    let Some(wall) = editor.wall else {
+        return;
+    };
+    if let Some(point) = wall.intersect(shot.origin, shot.direction) {
+        editor.practice_shots.push(point);
+    }
+    // how tight the spray stayed, perfect control lands everything on the first shot
+    let first = editor.practice_shots.first().copied().unwrap_or(wall.origin);
+    let total: f32 = editor
+        .practice_shots
+        .iter()
+        .map(|point| point.distance(first))
+        .sum();
+    let average = total / editor.practice_shots.len().max(1) as f32;
+    editor.status = format!(
+        "{} shots, {:.1} cm average from the first",
+        editor.practice_shots.len(),
+        average * 100.
+    );
+}
+
+pub fn update_recoil_editor(
+    mouse_button: Res<Input<MouseButton>>,
+    mut shot_events: EventReader<ShotFired>,
+    mut editor: ResMut<RecoilEditor>,
+    mut gun_query: Query<&mut GunController>,
+    view_query: Query<(&Camera, &GlobalTransform), With<PlayerView>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+) {
+    if !editor.open {
+        shot_events.clear();
+        return;
+    }
+    let editor = editor.as_mut();
+    for shot in shot_events.iter() {
+        match editor.mode {
+            PatternEditorMode::Edit => {}
+            PatternEditorMode::Record => record_shot(editor, shot),
+            PatternEditorMode::Practice => practice_shot(editor, shot),
+        }
+        editor.markers_dirty = true;
+    }
+
+    if editor.mode != PatternEditorMode::Edit {
+        return;
+    }
+    let (Ok(mut gun_controller), Ok((camera, view_transform)), Ok(window)) = (
+        gun_query.get_single_mut(),
+        view_query.get_single(),
+        windows.get_single(),
+    ) else {
+        return;
+    };
+    let (Some(wall), Some(cursor)) = (editor.wall, window.cursor_position()) else {
+        return;
+    };
+
+    if mouse_button.just_pressed(MouseButton::Left) {
+        editor.dragging = editor
+            .pattern
+            .iter()
+            .enumerate()
+            .filter_map(|(index, offset)| {
+                let screen = camera.world_to_viewport(view_transform, wall.point(*offset))?;
+                Some((index, screen.distance(cursor)))
+            })
+            .filter(|(_, distance)| *distance <= PICK_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index);
+        editor.markers_dirty = true;
+    }
+    if mouse_button.just_released(MouseButton::Left) {
+        editor.dragging = None;
+        editor.markers_dirty = true;
+    }
+    let Some(index) = editor.dragging else {
+        return;
+    };
+    let Some(point) = camera
+        .viewport_to_world(view_transform, cursor)
+        .and_then(|ray| wall.intersect(ray.origin, ray.direction))
+    else {
+        return;
+    };
+    let offset = wall.offset(point);
+    if editor.pattern[index] != offset {
+        editor.pattern[index] = offset;
+        gun_controller.spray_pattern = editor.pattern.clone();
+        editor.status = format!("point {} at {:.4}, {:.4}", index + 1, offset.x, offset.y);
+        editor.markers_dirty = true;
+    }
+}
+
+pub fn update_pattern_markers(
+    mut commands: Commands,
+    mut editor: ResMut<RecoilEditor>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    marker_query: Query<Entity, With<PatternMarker>>,
+) {
+    if !editor.markers_dirty {
+        return;
+    }
+    editor.markers_dirty = false;
+    for entity in marker_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(wall) = editor.wall.filter(|_| editor.open) else {
+        return;
+    };
+
+    if editor.marker_assets.is_none() {
+        let mut marker_material = |color: Color| {
+            materials.add(StandardMaterial {
+                base_color: color,
+                unlit: true,
+                ..default()
+            })
+        };
+        editor.marker_assets = Some(MarkerAssets {
+            mesh: meshes.add(Mesh::from(shape::UVSphere {
+                radius: 1.,
+                ..default()
+            })),
+            pattern: marker_material(Color::rgb(1., 0.8, 0.1)),
+            selected: marker_material(Color::WHITE),
+            shot: marker_material(Color::rgb(0.1, 0.8, 1.)),
+        });
+    }
+    let Some(assets) = &editor.marker_assets else {
+        return;
+    };
+    let scale = Vec3::splat(wall.distance * MARKER_SIZE);
+    let mut spawn_marker = |position: Vec3, material: &Handle<StandardMaterial>| {
+        commands.spawn((
+            PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(position).with_scale(scale),
+                ..default()
+            },
+            NotShadowCaster,
+            PatternMarker {},
+        ));
+    };
+
+    for (index, offset) in editor.shown_pattern().iter().enumerate() {
+        let material = if editor.dragging == Some(index) {
+            &assets.selected
+        } else {
+            &assets.pattern
+        };
+        spawn_marker(wall.point(*offset), material);
+    }
+    for point in editor.practice_shots.iter() {
+        spawn_marker(*point, &assets.shot);
+    }
+}
+
+fn editor_text(editor: &RecoilEditor, settings: &Settings) -> String {
+    let (mode, help) = match editor.mode {
+        PatternEditorMode::Edit => ("EDIT", "drag the points with the mouse"),
+        PatternEditorMode::Record => ("RECORD", "spray and pull the mouse to draw a new pattern"),
+        PatternEditorMode::Practice => ("PRACTICE", "spray at the wall to see how it compares"),
+    };
+    let keys = [
+        (Action::EditPattern, "edit"),
+        (Action::RecordPattern, "record"),
+        (Action::PracticePattern, "practice"),
+        (Action::SavePattern, "save"),
+        (Action::ToggleRecoilEditor, "close"),
+    ]
+    .iter()
+    .map(|(action, name)| {
+        let key = settings
+            .bindings
+            .bindings(*action)
+            .first()
+            .map_or("unbound".to_string(), |binding| binding.label().to_lowercase());
+        format!("{} {}", key, name)
+    })
+    .collect::<Vec<String>>()
+    .join("  ");
+    format!("PATTERN EDITOR - {}\n{}\n{}\n{}", mode, help, keys, editor.status)
+}
+
+pub fn update_pattern_editor_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    editor: Res<RecoilEditor>,
+    settings: Res<Settings>,
+    mut text_query: Query<(Entity, &mut Text), With<PatternEditorText>>,
+) {
+    if !editor.open {
+        for (entity, _) in text_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    if let Ok((_, mut text)) = text_query.get_single_mut() {
+        if editor.is_changed() || settings.is_changed() {
+            text.sections[0].value = editor_text(&editor, &settings);
+        }
+        return;
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            editor_text(&editor, &settings),
+            TextStyle {
+                font: asset_server.load("font.ttf"),
+                font_size: 22.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(10.),
+                top: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        }),
+        PatternEditorText {},
+    ));
+}
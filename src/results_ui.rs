@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+use crate::{
+    menu::{spawn_button, MenuButton},
+    personal_best::{unix_timestamp, LastMatchResult, MatchResult},
+};
+
+#[derive(Component)]
+pub struct ResultsScreen {}
+
+pub fn format_days_ago(timestamp: u64) -> String {
+    let days = unix_timestamp().saturating_sub(timestamp) / 86400;
+    match days {
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        _ => format!("{} days ago", days),
+    }
+}
+
+pub fn results_text(result: &MatchResult) -> String {
+    let mut text = format!(
+        "MATCH OVER\nSCORE: {}   ACCURACY: {:.1}%\nKILLS: {}   HEADSHOTS: {}\n",
+        result.record.score,
+        result.record.accuracy * 100.,
+        result.record.kills,
+        result.record.headshots,
+    );
+    match &result.previous_best {
+        Some(best) => {
+            let score_delta = result.record.score - best.score;
+            let accuracy_delta = (result.record.accuracy - best.accuracy) * 100.;
+            if result.new_best {
+                text.push_str("NEW PERSONAL BEST!\n");
+            }
+            text.push_str(&format!(
+                "PREVIOUS BEST: {} ({:+})   ACCURACY {:.1}% ({:+.1}%)\nset {} at sensitivity {:.2}, fov {:.0}",
+                best.score,
+                score_delta,
+                best.accuracy * 100.,
+                accuracy_delta,
+                format_days_ago(best.timestamp),
+                best.settings.sensitivity,
+                best.settings.fov,
+            ));
+        }
+        None => text.push_str("FIRST RECORDED RESULT - NEW PERSONAL BEST!"),
+    }
+    text
+}
+
+pub fn spawn_results_screen(
+    mut commands: Commands,
+    last_result: Res<LastMatchResult>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(result) = last_result.0.as_ref() else {
+        return;
+    };
+    let font = asset_server.load("font.ttf");
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                ..default()
+            },
+            ResultsScreen {},
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    results_text(result),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+            spawn_button(parent, font.clone(), "PLAY AGAIN", MenuButton::PlayAgain);
+            spawn_button(parent, font.clone(), "MAIN MENU", MenuButton::MainMenu);
+        });
+}
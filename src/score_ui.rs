@@ -1,12 +1,18 @@
 use bevy::prelude::*;
 
+use crate::game_mode::MatchStats;
+
 #[derive(Component)]
-pub struct ScoreText {
-    pub score: i32,
-}
+pub struct ScoreText {}
 
-pub fn update_score(mut score_query: Query<(&ScoreText, &mut Text)>) {
-    if let Ok((score_text, mut text)) = score_query.get_single_mut() {
-        text.sections[0].value = "SCORE: ".to_string() + &score_text.score.to_string();
+pub fn update_score(
+    match_stats: Res<MatchStats>,
+    mut score_query: Query<&mut Text, With<ScoreText>>,
+) {
+    if !match_stats.is_changed() {
+        return;
+    }
+    for mut text in score_query.iter_mut() {
+        text.sections[0].value = "SCORE ".to_string() + &match_stats.score().to_string();
     }
 }
@@ -0,0 +1,92 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+pub const CM_PER_INCH: f32 = 2.54;
+
+// degrees of yaw per mouse count at sensitivity 1 for each game's sensitivity scale
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum YawPreset {
+    Native,
+    Source,
+    Valorant,
+    Overwatch,
+    Fortnite,
+    Custom(f32),
+}
+
+pub const YAW_PRESETS: [YawPreset; 6] = [
+    YawPreset::Native,
+    YawPreset::Source,
+    YawPreset::Valorant,
+    YawPreset::Overwatch,
+    YawPreset::Fortnite,
+    YawPreset::Custom(0.022),
+];
+
+impl YawPreset {
+    pub fn yaw_degrees(&self) -> f32 {
+        match self {
+            // the game's own sensitivity scale, 1 / 900 radians per count
+            YawPreset::Native => (1. / 900_f32).to_degrees(),
+            YawPreset::Source => 0.022,
+            YawPreset::Valorant => 0.07,
+            YawPreset::Overwatch => 0.0066,
+            YawPreset::Fortnite => 0.5555,
+            YawPreset::Custom(yaw) => *yaw,
+        }
+    }
+    pub fn label(&self) -> String {
+        match self {
+            YawPreset::Native => "NATIVE".to_string(),
+            YawPreset::Source => "CS / SOURCE / APEX".to_string(),
+            YawPreset::Valorant => "VALORANT".to_string(),
+            YawPreset::Overwatch => "OVERWATCH".to_string(),
+            YawPreset::Fortnite => "FORTNITE".to_string(),
+            YawPreset::Custom(yaw) => format!("CUSTOM {:.4}", yaw),
+        }
+    }
+    pub fn is_custom(&self) -> bool {
+        matches!(self, YawPreset::Custom(_))
+    }
+    pub fn next(&self, step: i32) -> Self {
+        let index = YAW_PRESETS
+            .iter()
+            .position(|preset| std::mem::discriminant(preset) == std::mem::discriminant(self))
+            .unwrap_or(0) as i32;
+        let next = YAW_PRESETS[(index + step).rem_euclid(YAW_PRESETS.len() as i32) as usize];
+        // keep the user's own yaw when cycling back onto custom
+        match (next, self) {
+            (YawPreset::Custom(_), YawPreset::Custom(yaw)) => YawPreset::Custom(*yaw),
+            _ => next,
+        }
+    }
+}
+
+pub fn radians_per_count(sensitivity: f32, preset: YawPreset) -> f32 {
+    (sensitivity * preset.yaw_degrees()).to_radians()
+}
+
+// re-expresses a sensitivity in another game's scale so it turns the same amount per count
+pub fn convert_sensitivity(sensitivity: f32, from: YawPreset, to: YawPreset) -> f32 {
+    sensitivity * from.yaw_degrees() / to.yaw_degrees()
+}
+
+pub fn cm_per_360(radians_per_count: f32, dpi: f32) -> f32 {
+    if radians_per_count <= 0. || dpi <= 0. {
+        return f32::INFINITY;
+    }
+    let counts = 2. * PI / radians_per_count;
+    counts / dpi * CM_PER_INCH
+}
+
+// monitor distance matching on the vertical axis. a coefficient of 1 matches the top edge of
+// the screen, 0.5 halfway to it, and 0 (the limit) matches the zoom ratio at the crosshair
+pub fn monitor_distance_ratio(hip_fov: f32, ads_fov: f32, coefficient: f32) -> f32 {
+    let hip_half = (hip_fov / 2.).tan();
+    let ads_half = (ads_fov / 2.).tan();
+    if coefficient <= 0. {
+        return ads_half / hip_half;
+    }
+    (coefficient * ads_half).atan() / (coefficient * hip_half).atan()
+}
@@ -0,0 +1,844 @@
+use bevy::{
+    core_pipeline::bloom::BloomSettings,
+    prelude::*,
+    ui::FocusPolicy,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera_effects::CameraEffects,
+    config_file::{config_file_path, load_toml, save_toml},
+    controls_menu::ControlsMenu,
+    crosshair::CrosshairStyle,
+    hud::{default_hud_layout, HudWidgetLayout},
+    fps_camera::FPSCamera,
+    input_actions::InputBindings,
+    menu::{spawn_button, MenuButton},
+    mouse_look::MouseLookOptions,
+    sensitivity::{convert_sensitivity, cm_per_360, radians_per_count, YawPreset},
+    third_person::ThirdPersonCamera,
+    viewmodel::ViewmodelCamera,
+};
+
+const SETTINGS_FILE_NAME: &str = "settings.toml";
+
+pub const RESOLUTIONS: [[u32; 2]; 5] = [
+    [1280, 720],
+    [1600, 900],
+    [1920, 1080],
+    [2560, 1440],
+    [3840, 2160],
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WindowModeSetting {
+    Windowed,
+    BorderlessFullscreen,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    pub fn window_mode(&self) -> WindowMode {
+        match self {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+    pub fn next(&self, step: i32) -> Self {
+        let modes = [
+            WindowModeSetting::Windowed,
+            WindowModeSetting::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen,
+        ];
+        let index = modes.iter().position(|mode| mode == self).unwrap_or(0) as i32;
+        modes[(index + step).rem_euclid(modes.len() as i32) as usize]
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    // expressed in the scale of sensitivity_preset
+    pub sensitivity: f32,
+    pub sensitivity_preset: YawPreset,
+    pub mouse_dpi: f32,
+    pub horizontal_multiplier: f32,
+    pub vertical_multiplier: f32,
+    pub ads_multiplier: f32,
+    pub ads_monitor_distance: f32,
+    // seconds, 0 is off
+    pub mouse_smoothing: f32,
+    // 0 is off
+    pub mouse_acceleration: f32,
+    pub mouse_acceleration_cap: f32,
+    pub fov: f32,
+    // fov the gun is drawn with, independent of the world fov
+    pub viewmodel_fov: f32,
+    pub window_mode: WindowModeSetting,
+    pub resolution: [u32; 2],
+    pub vsync: bool,
+    pub bloom_intensity: f32,
+    pub master_volume: f32,
+    pub crosshair_visible: bool,
+    pub crosshair: CrosshairStyle,
+    // head bob, sway, landing dip and screen shake
+    pub camera_effects: bool,
+    pub third_person: bool,
+    // on top of the automatic scaling with window height
+    pub ui_scale: f32,
+    // share of the screen kept clear around the edges of the hud, for tvs that crop
+    pub hud_safe_area: f32,
+    pub hud_layout: Vec<HudWidgetLayout>,
+    pub bindings: InputBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            sensitivity: 0.35,
+            sensitivity_preset: YawPreset::Native,
+            mouse_dpi: 800.,
+            horizontal_multiplier: 1.,
+            vertical_multiplier: 1.,
+            ads_multiplier: 1.,
+            ads_monitor_distance: 0.,
+            mouse_smoothing: 0.,
+            mouse_acceleration: 0.,
+            mouse_acceleration_cap: 2.,
+            fov: 103.,
+            viewmodel_fov: 70.,
+            window_mode: WindowModeSetting::BorderlessFullscreen,
+            resolution: [1920, 1080],
+            vsync: true,
+            bloom_intensity: 0.2,
+            master_volume: 1.,
+            crosshair_visible: true,
+            crosshair: CrosshairStyle::default(),
+            camera_effects: true,
+            third_person: false,
+            ui_scale: 1.,
+            hud_safe_area: 0.02,
+            hud_layout: default_hud_layout(),
+            bindings: InputBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let mut settings: Settings = match config_file_path(SETTINGS_FILE_NAME) {
+            Some(path) => load_toml(&path),
+            None => {
+                warn!("no platform config directory, settings will not be saved");
+                Settings::default()
+            }
+        };
+        settings.bindings.add_missing_defaults();
+        settings
+    }
+    pub fn save(&self) {
+        if let Some(path) = config_file_path(SETTINGS_FILE_NAME) {
+            save_toml(&path, self);
+        }
+    }
+    pub fn camera_sensitivity(&self) -> f32 {
+        radians_per_count(self.sensitivity, self.sensitivity_preset) * self.horizontal_multiplier
+    }
+    pub fn camera_vertical_sensitivity(&self) -> f32 {
+        radians_per_count(self.sensitivity, self.sensitivity_preset) * self.vertical_multiplier
+    }
+    pub fn cm_per_360(&self) -> f32 {
+        cm_per_360(self.camera_sensitivity(), self.mouse_dpi)
+    }
+    pub fn mouse_look_options(&self) -> MouseLookOptions {
+        MouseLookOptions {
+            smoothing: self.mouse_smoothing,
+            acceleration: self.mouse_acceleration,
+            acceleration_cap: self.mouse_acceleration_cap,
+        }
+    }
+    pub fn fov_radians(&self) -> f32 {
+        self.fov.to_radians()
+    }
+    pub fn viewmodel_fov_radians(&self) -> f32 {
+        self.viewmodel_fov.to_radians()
+    }
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+    pub fn window(&self) -> Window {
+        Window {
+            position: WindowPosition::Centered(MonitorSelection::Primary),
+            resolution: (self.resolution[0] as f32, self.resolution[1] as f32).into(),
+            mode: self.window_mode.window_mode(),
+            present_mode: self.present_mode(),
+            ..default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    SensitivityPreset,
+    Sensitivity,
+    CustomYaw,
+    MouseDpi,
+    Cm360,
+    HorizontalMultiplier,
+    VerticalMultiplier,
+    AdsMultiplier,
+    AdsMonitorDistance,
+    MouseSmoothing,
+    MouseAcceleration,
+    Fov,
+    ViewmodelFov,
+    Resolution,
+    WindowMode,
+    Vsync,
+    Bloom,
+    MasterVolume,
+    Crosshair,
+    CrosshairSize,
+    CrosshairGap,
+    CrosshairThickness,
+    CrosshairCode,
+    CameraEffects,
+    ThirdPerson,
+    UiScale,
+    HudSafeArea,
+    GamepadDeadZone,
+    GamepadLookSpeed,
+    GamepadLookCurve,
+    GamepadInvertY,
+}
+
+impl SettingKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SettingKind::SensitivityPreset => "SENSITIVITY UNITS",
+            SettingKind::Sensitivity => "SENSITIVITY",
+            SettingKind::CustomYaw => "CUSTOM YAW",
+            SettingKind::MouseDpi => "MOUSE DPI",
+            SettingKind::Cm360 => "CM / 360",
+            SettingKind::HorizontalMultiplier => "HORIZONTAL MULTIPLIER",
+            SettingKind::VerticalMultiplier => "VERTICAL MULTIPLIER",
+            SettingKind::AdsMultiplier => "ADS MULTIPLIER",
+            SettingKind::AdsMonitorDistance => "ADS MONITOR DISTANCE",
+            SettingKind::MouseSmoothing => "MOUSE SMOOTHING",
+            SettingKind::MouseAcceleration => "MOUSE ACCELERATION",
+            SettingKind::Fov => "FOV",
+            SettingKind::ViewmodelFov => "VIEWMODEL FOV",
+            SettingKind::Resolution => "RESOLUTION",
+            SettingKind::WindowMode => "WINDOW MODE",
+            SettingKind::Vsync => "VSYNC",
+            SettingKind::Bloom => "BLOOM",
+            SettingKind::MasterVolume => "MASTER VOLUME",
+            SettingKind::Crosshair => "CROSSHAIR",
+            SettingKind::CrosshairSize => "CROSSHAIR SIZE",
+            SettingKind::CrosshairGap => "CROSSHAIR GAP",
+            SettingKind::CrosshairThickness => "CROSSHAIR THICKNESS",
+            SettingKind::CrosshairCode => "CROSSHAIR CODE",
+            SettingKind::CameraEffects => "CAMERA EFFECTS",
+            SettingKind::ThirdPerson => "THIRD PERSON",
+            SettingKind::UiScale => "UI SCALE",
+            SettingKind::HudSafeArea => "HUD SAFE AREA",
+            SettingKind::GamepadDeadZone => "STICK DEAD ZONE",
+            SettingKind::GamepadLookSpeed => "STICK LOOK SPEED",
+            SettingKind::GamepadLookCurve => "STICK RESPONSE CURVE",
+            SettingKind::GamepadInvertY => "STICK INVERT Y",
+        }
+    }
+    pub fn value(&self, settings: &Settings) -> String {
+        let on_off = |value: bool| if value { "ON" } else { "OFF" }.to_string();
+        match self {
+            SettingKind::SensitivityPreset => settings.sensitivity_preset.label(),
+            SettingKind::Sensitivity => format!("{:.3}", settings.sensitivity),
+            SettingKind::CustomYaw => format!("{:.4}", settings.sensitivity_preset.yaw_degrees()),
+            SettingKind::MouseDpi => format!("{:.0}", settings.mouse_dpi),
+            SettingKind::Cm360 => format!("{:.1}", settings.cm_per_360()),
+            SettingKind::HorizontalMultiplier => format!("{:.2}", settings.horizontal_multiplier),
+            SettingKind::VerticalMultiplier => format!("{:.2}", settings.vertical_multiplier),
+            SettingKind::AdsMultiplier => format!("{:.2}", settings.ads_multiplier),
+            SettingKind::AdsMonitorDistance => {
+                format!("{:.0}%", settings.ads_monitor_distance * 100.)
+            }
+            SettingKind::MouseSmoothing => match settings.mouse_smoothing {
+                smoothing if smoothing <= 0. => "OFF".to_string(),
+                smoothing => format!("{:.0} MS", smoothing * 1000.),
+            },
+            SettingKind::MouseAcceleration => match settings.mouse_acceleration {
+                acceleration if acceleration <= 0. => "OFF".to_string(),
+                acceleration => format!("{:.2}", acceleration),
+            },
+            SettingKind::Fov => format!("{:.0}", settings.fov),
+            SettingKind::ViewmodelFov => format!("{:.0}", settings.viewmodel_fov),
+            SettingKind::Resolution => {
+                format!("{}x{}", settings.resolution[0], settings.resolution[1])
+            }
+            SettingKind::WindowMode => format!("{:?}", settings.window_mode).to_uppercase(),
+            SettingKind::Vsync => on_off(settings.vsync),
+            SettingKind::Bloom => format!("{:.2}", settings.bloom_intensity),
+            SettingKind::MasterVolume => format!("{:.0}%", settings.master_volume * 100.),
+            SettingKind::Crosshair => on_off(settings.crosshair_visible),
+            SettingKind::CrosshairSize => format!("{:.1}", settings.crosshair.length),
+            SettingKind::CrosshairGap => format!("{:.1}", settings.crosshair.gap),
+            SettingKind::CrosshairThickness => format!("{:.1}", settings.crosshair.thickness),
+            SettingKind::CrosshairCode => settings.crosshair.share_code(),
+            SettingKind::CameraEffects => on_off(settings.camera_effects),
+            SettingKind::ThirdPerson => on_off(settings.third_person),
+            SettingKind::UiScale => format!("{:.0}%", settings.ui_scale * 100.),
+            SettingKind::HudSafeArea => format!("{:.0}%", settings.hud_safe_area * 100.),
+            SettingKind::GamepadDeadZone => format!("{:.2}", settings.bindings.gamepad_dead_zone),
+            SettingKind::GamepadLookSpeed => {
+                format!("{:.0}", settings.bindings.gamepad_look_speed)
+            }
+            SettingKind::GamepadLookCurve => {
+                format!("{:.1}", settings.bindings.gamepad_look_curve)
+            }
+            SettingKind::GamepadInvertY => on_off(settings.bindings.gamepad_invert_y),
+        }
+    }
+    pub fn adjust(&self, settings: &mut Settings, step: i32) {
+        let step_f = step as f32;
+        match self {
+            SettingKind::SensitivityPreset => {
+                let preset = settings.sensitivity_preset.next(step);
+                settings.sensitivity =
+                    convert_sensitivity(settings.sensitivity, settings.sensitivity_preset, preset);
+                settings.sensitivity_preset = preset;
+            }
+            SettingKind::Sensitivity => {
+                // step by one percent so every game's scale is usable from the arrows
+                let value = settings.sensitivity * (1. + step_f * 0.01);
+                self.set_value(settings, value);
+            }
+            SettingKind::CustomYaw => {
+                let value = settings.sensitivity_preset.yaw_degrees() * (1. + step_f * 0.01);
+                self.set_value(settings, value);
+            }
+            SettingKind::MouseDpi => {
+                self.set_value(settings, settings.mouse_dpi + step_f * 50.)
+            }
+            SettingKind::Cm360 => {}
+            SettingKind::HorizontalMultiplier => {
+                self.set_value(settings, settings.horizontal_multiplier + step_f * 0.05)
+            }
+            SettingKind::VerticalMultiplier => {
+                self.set_value(settings, settings.vertical_multiplier + step_f * 0.05)
+            }
+            SettingKind::AdsMultiplier => {
+                self.set_value(settings, settings.ads_multiplier + step_f * 0.05)
+            }
+            SettingKind::AdsMonitorDistance => {
+                self.set_value(settings, settings.ads_monitor_distance + step_f * 0.25)
+            }
+            SettingKind::MouseSmoothing => {
+                settings.mouse_smoothing =
+                    (settings.mouse_smoothing + step_f * 0.005).clamp(0., 0.05)
+            }
+            SettingKind::MouseAcceleration => {
+                settings.mouse_acceleration =
+                    (settings.mouse_acceleration + step_f * 0.05).clamp(0., 2.)
+            }
+            SettingKind::Fov => settings.fov = (settings.fov + step_f).clamp(60., 130.),
+            SettingKind::ViewmodelFov => {
+                settings.viewmodel_fov = (settings.viewmodel_fov + step_f).clamp(50., 110.)
+            }
+            SettingKind::Resolution => {
+                let index = RESOLUTIONS
+                    .iter()
+                    .position(|resolution| *resolution == settings.resolution)
+                    .unwrap_or(2) as i32;
+                settings.resolution =
+                    RESOLUTIONS[(index + step).rem_euclid(RESOLUTIONS.len() as i32) as usize];
+            }
+            SettingKind::WindowMode => settings.window_mode = settings.window_mode.next(step),
+            SettingKind::Vsync => settings.vsync = !settings.vsync,
+            SettingKind::Bloom => {
+                settings.bloom_intensity = (settings.bloom_intensity + step_f * 0.05).clamp(0., 1.)
+            }
+            SettingKind::MasterVolume => {
+                settings.master_volume = (settings.master_volume + step_f * 0.05).clamp(0., 1.)
+            }
+            SettingKind::Crosshair => settings.crosshair_visible = !settings.crosshair_visible,
+            SettingKind::CrosshairSize => {
+                let crosshair = &mut settings.crosshair;
+                crosshair.length = (crosshair.length + step_f * 0.5).clamp(0., 32.)
+            }
+            SettingKind::CrosshairGap => {
+                let crosshair = &mut settings.crosshair;
+                crosshair.gap = (crosshair.gap + step_f * 0.5).clamp(-8., 32.)
+            }
+            SettingKind::CrosshairThickness => {
+                let crosshair = &mut settings.crosshair;
+                crosshair.thickness = (crosshair.thickness + step_f * 0.5).clamp(0.5, 8.)
+            }
+            SettingKind::CrosshairCode => {}
+            SettingKind::CameraEffects => settings.camera_effects = !settings.camera_effects,
+            SettingKind::ThirdPerson => settings.third_person = !settings.third_person,
+            SettingKind::UiScale => {
+                settings.ui_scale = (settings.ui_scale + step_f * 0.05).clamp(0.5, 2.)
+            }
+            SettingKind::HudSafeArea => {
+                settings.hud_safe_area = (settings.hud_safe_area + step_f * 0.01).clamp(0., 0.1)
+            }
+            SettingKind::GamepadDeadZone => {
+                let bindings = &mut settings.bindings;
+                bindings.gamepad_dead_zone =
+                    (bindings.gamepad_dead_zone + step_f * 0.01).clamp(0., 0.5)
+            }
+            SettingKind::GamepadLookSpeed => {
+                let bindings = &mut settings.bindings;
+                bindings.gamepad_look_speed =
+                    (bindings.gamepad_look_speed + step_f * 10.).clamp(30., 720.)
+            }
+            SettingKind::GamepadLookCurve => {
+                let bindings = &mut settings.bindings;
+                bindings.gamepad_look_curve =
+                    (bindings.gamepad_look_curve + step_f * 0.1).clamp(1., 4.)
+            }
+            SettingKind::GamepadInvertY => {
+                settings.bindings.gamepad_invert_y = !settings.bindings.gamepad_invert_y
+            }
+        }
+    }
+    pub fn editable(&self) -> bool {
+        matches!(
+            self,
+            SettingKind::Sensitivity
+                | SettingKind::CustomYaw
+                | SettingKind::MouseDpi
+                | SettingKind::HorizontalMultiplier
+                | SettingKind::VerticalMultiplier
+                | SettingKind::AdsMultiplier
+                | SettingKind::AdsMonitorDistance
+                | SettingKind::CrosshairCode
+        )
+    }
+    pub fn set_value(&self, settings: &mut Settings, value: f32) {
+        match self {
+            SettingKind::Sensitivity => settings.sensitivity = value.clamp(0.0001, 1000.),
+            SettingKind::CustomYaw => {
+                // typing a yaw switches to a custom scale, keeping the turn rate
+                let preset = YawPreset::Custom(value.clamp(0.00001, 100.));
+                settings.sensitivity =
+                    convert_sensitivity(settings.sensitivity, settings.sensitivity_preset, preset);
+                settings.sensitivity_preset = preset;
+            }
+            SettingKind::MouseDpi => settings.mouse_dpi = value.clamp(50., 32000.),
+            SettingKind::HorizontalMultiplier => {
+                settings.horizontal_multiplier = value.clamp(0.05, 10.)
+            }
+            SettingKind::VerticalMultiplier => {
+                settings.vertical_multiplier = value.clamp(0.05, 10.)
+            }
+            SettingKind::AdsMultiplier => settings.ads_multiplier = value.clamp(0.05, 10.),
+            SettingKind::AdsMonitorDistance => {
+                settings.ads_monitor_distance = value.clamp(0., 1.)
+            }
+            _ => {}
+        }
+    }
+}
+
+// one page on screen at a time, so the menu still fits with the ui scaled up
+pub const SETTING_PAGES: [(&str, &[SettingKind]); 4] = [
+    (
+        "MOUSE",
+        &[
+            SettingKind::SensitivityPreset,
+            SettingKind::Sensitivity,
+            SettingKind::CustomYaw,
+            SettingKind::MouseDpi,
+            SettingKind::Cm360,
+            SettingKind::HorizontalMultiplier,
+            SettingKind::VerticalMultiplier,
+            SettingKind::AdsMultiplier,
+            SettingKind::AdsMonitorDistance,
+            SettingKind::MouseSmoothing,
+            SettingKind::MouseAcceleration,
+        ],
+    ),
+    (
+        "GENERAL",
+        &[
+            SettingKind::Fov,
+            SettingKind::ViewmodelFov,
+            SettingKind::Resolution,
+            SettingKind::WindowMode,
+            SettingKind::Vsync,
+            SettingKind::Bloom,
+            SettingKind::MasterVolume,
+            SettingKind::CameraEffects,
+            SettingKind::ThirdPerson,
+        ],
+    ),
+    (
+        "HUD",
+        &[
+            SettingKind::Crosshair,
+            SettingKind::CrosshairSize,
+            SettingKind::CrosshairGap,
+            SettingKind::CrosshairThickness,
+            SettingKind::CrosshairCode,
+            SettingKind::UiScale,
+            SettingKind::HudSafeArea,
+        ],
+    ),
+    (
+        "GAMEPAD",
+        &[
+            SettingKind::GamepadDeadZone,
+            SettingKind::GamepadLookSpeed,
+            SettingKind::GamepadLookCurve,
+            SettingKind::GamepadInvertY,
+        ],
+    ),
+];
+
+#[derive(Resource, Default)]
+pub struct SettingsMenu {
+    pub open: bool,
+    pub page: usize,
+    pub editing: Option<SettingKind>,
+    pub edit_buffer: String,
+}
+
+#[derive(Component)]
+pub struct SettingsScreen {
+    pub page: usize,
+}
+
+#[derive(Component)]
+pub struct SettingsPageButton(pub usize);
+
+#[derive(Component)]
+pub struct SettingButton {
+    pub kind: SettingKind,
+    pub step: i32,
+}
+
+#[derive(Component)]
+pub struct SettingValueText(pub SettingKind);
+
+#[derive(Component)]
+pub struct SettingValueButton(pub SettingKind);
+
+pub fn setting_value_text(settings: &Settings, settings_menu: &SettingsMenu, kind: SettingKind) -> String {
+    if settings_menu.editing == Some(kind) {
+        return format!("{}_", settings_menu.edit_buffer);
+    }
+    kind.value(settings)
+}
+
+pub fn apply_settings(
+    settings: Res<Settings>,
+    mut camera_query: Query<&mut FPSCamera>,
+    mut effects_query: Query<&mut CameraEffects>,
+    mut third_person_query: Query<&mut ThirdPersonCamera>,
+    mut bloom_query: Query<&mut BloomSettings>,
+    mut viewmodel_query: Query<&mut Projection, With<ViewmodelCamera>>,
+    mut primary_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut camera in camera_query.iter_mut() {
+        camera.sensitivity = settings.camera_sensitivity();
+        camera.vertical_sensitivity = settings.camera_vertical_sensitivity();
+    }
+    for mut effects in effects_query.iter_mut() {
+        effects.enabled = settings.camera_effects;
+    }
+    for mut third_person in third_person_query.iter_mut() {
+        third_person.enabled = settings.third_person;
+    }
+    for mut bloom_settings in bloom_query.iter_mut() {
+        bloom_settings.intensity = settings.bloom_intensity;
+    }
+    for mut projection in viewmodel_query.iter_mut() {
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = settings.viewmodel_fov_radians();
+        }
+    }
+    let Ok(mut primary) = primary_query.get_single_mut() else {
+        return;
+    };
+    let mode = settings.window_mode.window_mode();
+    if primary.mode != mode {
+        primary.mode = mode;
+    }
+    let (width, height) = (settings.resolution[0] as f32, settings.resolution[1] as f32);
+    if primary.resolution.width() != width || primary.resolution.height() != height {
+        primary.resolution.set(width, height);
+    }
+    let present_mode = settings.present_mode();
+    if primary.present_mode != present_mode {
+        primary.present_mode = present_mode;
+    }
+}
+
+pub fn close_settings_menu(
+    mut settings_menu: ResMut<SettingsMenu>,
+    mut controls_menu: ResMut<ControlsMenu>,
+    settings: Res<Settings>,
+) {
+    if settings_menu.open || controls_menu.open {
+        settings_menu.open = false;
+        settings_menu.editing = None;
+        controls_menu.open = false;
+        controls_menu.waiting_for = None;
+        settings.save();
+    }
+}
+
+pub fn update_settings_screen(
+    mut commands: Commands,
+    settings_menu: Res<SettingsMenu>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    screen_query: Query<(Entity, &SettingsScreen)>,
+    mut value_query: Query<(&SettingValueText, &mut Text)>,
+) {
+    if settings.is_changed() || settings_menu.is_changed() {
+        for (value_text, mut text) in value_query.iter_mut() {
+            text.sections[0].value = setting_value_text(&settings, &settings_menu, value_text.0);
+        }
+    }
+    let spawned_page = screen_query.iter().next().map(|(_, screen)| screen.page);
+    let page = settings_menu.page.min(SETTING_PAGES.len() - 1);
+    let wanted_page = settings_menu.open.then_some(page);
+    if spawned_page == wanted_page {
+        return;
+    }
+    for (entity, _) in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !settings_menu.open {
+        return;
+    }
+    let font = asset_server.load("font.ttf");
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 22.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.05, 0.05, 0.05).into(),
+                focus_policy: FocusPolicy::Block,
+                ..default()
+            },
+            SettingsScreen { page },
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::bottom(Val::Px(10.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|tabs| {
+                    for (index, (label, _)) in SETTING_PAGES.iter().enumerate() {
+                        let color = if index == page {
+                            Color::rgb(0.3, 0.3, 0.3)
+                        } else {
+                            Color::rgb(0.15, 0.15, 0.15)
+                        };
+                        tabs.spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(150.0), Val::Px(36.0)),
+                                    margin: UiRect::horizontal(Val::Px(4.0)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                background_color: color.into(),
+                                ..default()
+                            },
+                            SettingsPageButton(index),
+                        ))
+                        .with_children(|tab| {
+                            tab.spawn(TextBundle::from_section(*label, text_style.clone()));
+                        });
+                    }
+                });
+            for kind in SETTING_PAGES[page].1.iter().copied() {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(640.0), Val::Px(30.0)),
+                            margin: UiRect::all(Val::Px(1.0)),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn(
+                            TextBundle::from_section(kind.label(), text_style.clone())
+                                .with_style(Style {
+                                    size: Size::new(Val::Px(300.0), Val::Auto),
+                                    ..default()
+                                }),
+                        );
+                        spawn_setting_button(row, &text_style, "<", kind, -1);
+                        let mut value_cell = row.spawn(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(220.0), Val::Px(28.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        });
+                        // typed entry for values that come from other games or hardware
+                        if kind.editable() {
+                            value_cell.insert((
+                                Button,
+                                Interaction::default(),
+                                BackgroundColor(Color::rgb(0.1, 0.1, 0.1)),
+                                SettingValueButton(kind),
+                            ));
+                        }
+                        value_cell.with_children(|cell| {
+                            cell.spawn((
+                                TextBundle::from_section(
+                                    setting_value_text(&settings, &settings_menu, kind),
+                                    text_style.clone(),
+                                ),
+                                SettingValueText(kind),
+                            ));
+                        });
+                        spawn_setting_button(row, &text_style, ">", kind, 1);
+                    });
+            }
+            parent.spawn(NodeBundle::default()).with_children(|buttons| {
+                spawn_button(buttons, font.clone(), "CONTROLS", MenuButton::Controls);
+                spawn_button(buttons, font, "BACK", MenuButton::CloseSettings);
+            });
+        });
+}
+
+fn spawn_setting_button(
+    parent: &mut ChildBuilder,
+    text_style: &TextStyle,
+    label: &str,
+    kind: SettingKind,
+    step: i32,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(40.0), Val::Px(30.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                ..default()
+            },
+            SettingButton { kind, step },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, text_style.clone()));
+        });
+}
+
+pub fn handle_setting_buttons(
+    interaction_query: Query<(&Interaction, &SettingButton), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            button.kind.adjust(&mut settings, button.step);
+        }
+    }
+}
+
+pub fn handle_settings_page_buttons(
+    interaction_query: Query<(&Interaction, &SettingsPageButton), Changed<Interaction>>,
+    mut settings_menu: ResMut<SettingsMenu>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Clicked && settings_menu.page != button.0 {
+            settings_menu.page = button.0;
+            settings_menu.editing = None;
+        }
+    }
+}
+
+pub fn handle_setting_value_buttons(
+    interaction_query: Query<(&Interaction, &SettingValueButton), Changed<Interaction>>,
+    mut settings_menu: ResMut<SettingsMenu>,
+    settings: Res<Settings>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            settings_menu.editing = Some(button.0);
+            settings_menu.edit_buffer = button.0.value(&settings).trim_end_matches('%').to_string();
+        }
+    }
+}
+
+pub fn edit_setting_value(
+    mut settings_menu: ResMut<SettingsMenu>,
+    mut settings: ResMut<Settings>,
+    mut character_reader: EventReader<ReceivedCharacter>,
+    key: Res<Input<KeyCode>>,
+) {
+    let Some(kind) = settings_menu.editing else {
+        character_reader.clear();
+        return;
+    };
+    for event in character_reader.iter() {
+        // share codes are letters and digits, everything else is a number
+        let accepted = if kind == SettingKind::CrosshairCode {
+            event.char.is_ascii_alphanumeric()
+        } else {
+            event.char.is_ascii_digit() || event.char == '.'
+        };
+        if accepted {
+            settings_menu.edit_buffer.push(event.char);
+        }
+    }
+    if key.just_pressed(KeyCode::Back) {
+        settings_menu.edit_buffer.pop();
+    }
+    if key.just_pressed(KeyCode::Escape) {
+        settings_menu.editing = None;
+    }
+    if key.just_pressed(KeyCode::Return) || key.just_pressed(KeyCode::NumpadEnter) {
+        if kind == SettingKind::CrosshairCode {
+            // a code that doesn't decode leaves the crosshair as it was
+            if let Some(crosshair) = CrosshairStyle::from_share_code(&settings_menu.edit_buffer) {
+                settings.crosshair = crosshair;
+            }
+        } else if let Ok(mut value) = settings_menu.edit_buffer.parse::<f32>() {
+            if kind == SettingKind::AdsMonitorDistance {
+                value /= 100.;
+            }
+            kind.set_value(&mut settings, value);
+        }
+        settings_menu.editing = None;
+    }
+}
@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+
+use crate::{fps_movement::PlayerLanded, settings::Settings};
+
+pub const LANDING_SOUND: &str = "sounds/land.wav";
+
+pub fn play_landing_sound(
+    mut landed_events: EventReader<PlayerLanded>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+) {
+    for landed in landed_events.iter() {
+        // already loaded by the loading screen, this only fetches the handle
+        let sound: Handle<AudioSource> = asset_server.load(LANDING_SOUND);
+        let loudness = (landed.impact_speed / 15.).clamp(0.3, 1.);
+        audio.play_with_settings(
+            sound,
+            PlaybackSettings::ONCE.with_volume(settings.master_volume * loudness),
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    enemy::Enemy,
+    fps_camera::{FPSCamera, PlayerView},
+    fps_movement::FPSMovement,
+    gun_control::GunController,
+    input_actions::{Action, InputActions},
+    lock_cursor::CursorLockState,
+    mouse_look::accumulate_motion,
+    settings::Settings,
+};
+
+// enemies have no camera of their own, this is roughly where the player's sits above its feet
+pub const ENEMY_EYE_HEIGHT: f32 = 1.4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SpectatorMode {
+    FreeFly,
+    FirstPerson,
+    ThirdPerson,
+}
+
+#[derive(Resource)]
+pub struct Spectator {
+    pub active: bool,
+    pub mode: SpectatorMode,
+    pub target: Option<Entity>,
+
+    pub position: Vec3,
+    // x is pitch and y is yaw, relative to the target's facing when following in first person
+    pub rotation: Vec2,
+    pub speed: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub fast_multiplier: f32,
+    pub follow_distance: f32,
+}
+
+impl Default for Spectator {
+    fn default() -> Self {
+        Spectator {
+            active: false,
+            mode: SpectatorMode::FreeFly,
+            target: None,
+            position: Vec3::ZERO,
+            rotation: Vec2::ZERO,
+            speed: 8.,
+            min_speed: 1.,
+            max_speed: 64.,
+            fast_multiplier: 3.,
+            follow_distance: 4.,
+        }
+    }
+}
+
+impl Spectator {
+    pub fn look_rotation(&self) -> Quat {
+        Quat::from_rotation_y(self.rotation.y) * Quat::from_rotation_x(self.rotation.x)
+    }
+}
+
+pub fn not_spectating(spectator: Res<Spectator>) -> bool {
+    !spectator.active
+}
+
+// how far a camera can sit from the pivot along the direction before something solid is in the way
+pub fn camera_arm_length(
+    rapier_context: &RapierContext,
+    pivot: Vec3,
+    direction: Vec3,
+    distance: f32,
+    radius: f32,
+    filter: QueryFilter,
+) -> f32 {
+    match rapier_context.cast_shape(
+        pivot,
+        Quat::IDENTITY,
+        direction * distance,
+        &Collider::ball(radius),
+        1.,
+        filter,
+    ) {
+        Some((_, toi)) => distance * toi.toi,
+        None => distance,
+    }
+}
+
+// the player first, then every enemy in a stable order
+fn spectator_targets(
+    player_query: &Query<Entity, With<FPSMovement>>,
+    enemy_query: &Query<Entity, With<Enemy>>,
+) -> Vec<Entity> {
+    let mut enemies: Vec<Entity> = enemy_query.iter().collect();
+    enemies.sort();
+    player_query.iter().chain(enemies).collect()
+}
+
+fn cycle_target(targets: &[Entity], current: Option<Entity>, step: i32) -> Option<Entity> {
+    if targets.is_empty() {
+        return None;
+    }
+    let len = targets.len() as i32;
+    let index = match current.and_then(|current| targets.iter().position(|t| *t == current)) {
+        Some(index) => (index as i32 + step).rem_euclid(len),
+        None if step < 0 => len - 1,
+        None => 0,
+    };
+    Some(targets[index as usize])
+}
+
+pub fn toggle_spectator(
+    actions: Res<InputActions>,
+    mut spectator: ResMut<Spectator>,
+    player_query: Query<(&GlobalTransform, &FPSCamera)>,
+    mut gun_query: Query<&mut GunController>,
+) {
+    if !actions.just_pressed(Action::Spectate) {
+        return;
+    }
+    spectator.active = !spectator.active;
+    if !spectator.active {
+        return;
+    }
+    // start flying from wherever the player was looking
+    spectator.mode = SpectatorMode::FreeFly;
+    spectator.target = None;
+    if let Ok((transform, camera)) = player_query.get_single() {
+        spectator.position = transform.translation();
+        spectator.rotation = Vec2::new(camera.rotation.x, camera.rotation.y);
+    }
+    for mut gun_controller in gun_query.iter_mut() {
+        gun_controller.shoot = false;
+        gun_controller.aiming_down_sights = false;
+    }
+}
+
+pub fn update_spectator(
+    settings: Res<Settings>,
+    cursor_lock_state: Res<CursorLockState>,
+    actions: Res<InputActions>,
+    mut motion_evr: EventReader<MouseMotion>,
+    mut wheel_evr: EventReader<MouseWheel>,
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut spectator: ResMut<Spectator>,
+    player_query: Query<Entity, With<FPSMovement>>,
+    enemy_query: Query<Entity, With<Enemy>>,
+    target_query: Query<(&GlobalTransform, Option<&FPSCamera>), Without<PlayerView>>,
+    mut view_query: Query<(&mut Transform, &Parent), With<PlayerView>>,
+) {
+    let raw_motion = accumulate_motion(motion_evr.iter().map(|ev| &ev.delta));
+    let wheel: f32 = wheel_evr.iter().map(|ev| ev.y.signum()).sum();
+    if !spectator.active {
+        return;
+    }
+
+    let mut look = actions.gamepad_look;
+    if cursor_lock_state.state {
+        look += Vec2::new(
+            raw_motion.x * settings.camera_sensitivity(),
+            -raw_motion.y * settings.camera_vertical_sensitivity(),
+        );
+    }
+    let pitch_limit = 88_f32.to_radians();
+    spectator.rotation.y -= look.x;
+    spectator.rotation.x = (spectator.rotation.x + look.y).clamp(-pitch_limit, pitch_limit);
+
+    if actions.just_pressed(Action::SpectatorMode) {
+        spectator.mode = match spectator.mode {
+            SpectatorMode::FreeFly => SpectatorMode::FirstPerson,
+            SpectatorMode::FirstPerson => SpectatorMode::ThirdPerson,
+            SpectatorMode::ThirdPerson => SpectatorMode::FreeFly,
+        };
+        // first person looks relative to the target, so start straight down its sights
+        if spectator.mode == SpectatorMode::FirstPerson {
+            spectator.rotation = Vec2::ZERO;
+        }
+    }
+    let step = if actions.just_pressed(Action::SpectatorNext) {
+        1
+    } else if actions.just_pressed(Action::SpectatorPrevious) {
+        -1
+    } else {
+        0
+    };
+    let targets = spectator_targets(&player_query, &enemy_query);
+    if step != 0 {
+        spectator.target = cycle_target(&targets, spectator.target, step);
+        if spectator.mode == SpectatorMode::FreeFly {
+            spectator.mode = SpectatorMode::ThirdPerson;
+        }
+    }
+    // a despawned target falls back to the next one in line
+    if spectator.mode != SpectatorMode::FreeFly
+        && spectator.target.map_or(true, |target| !targets.contains(&target))
+    {
+        spectator.target = cycle_target(&targets, None, 1);
+    }
+
+    let target = spectator
+        .target
+        .filter(|_| spectator.mode != SpectatorMode::FreeFly)
+        .and_then(|target| target_query.get(target).ok());
+    let view = match (spectator.mode, target) {
+        (SpectatorMode::FirstPerson, Some((target_transform, fps_camera))) => {
+            let (_, rotation, translation) = target_transform.to_scale_rotation_translation();
+            let (eye, facing) = match fps_camera {
+                Some(fps_camera) => (
+                    translation,
+                    Quat::from_rotation_y(fps_camera.rotation.y)
+                        * Quat::from_rotation_x(fps_camera.rotation.x),
+                ),
+                None => (translation + Vec3::Y * ENEMY_EYE_HEIGHT, rotation),
+            };
+            Transform::from_translation(eye).with_rotation(facing * spectator.look_rotation())
+        }
+        (SpectatorMode::ThirdPerson, Some((target_transform, fps_camera))) => {
+            let mut pivot = target_transform.translation();
+            if fps_camera.is_none() {
+                pivot += Vec3::Y * ENEMY_EYE_HEIGHT;
+            }
+            if wheel != 0. {
+                spectator.follow_distance = (spectator.follow_distance - wheel).clamp(1., 20.);
+            }
+            let rotation = spectator.look_rotation();
+            let back = rotation * Vec3::Z;
+            let distance = camera_arm_length(
+                &rapier_context,
+                pivot,
+                back,
+                spectator.follow_distance,
+                0.2,
+                QueryFilter::only_fixed().exclude_sensors(),
+            );
+            Transform::from_translation(pivot + back * distance).with_rotation(rotation)
+        }
+        _ => {
+            if wheel != 0. {
+                let speed = spectator.speed * 1.25_f32.powf(wheel);
+                spectator.speed = speed.clamp(spectator.min_speed, spectator.max_speed);
+            }
+            let rotation = spectator.look_rotation();
+            let mut direction = rotation * Vec3::new(actions.move_axis.x, 0., -actions.move_axis.y);
+            if actions.pressed(Action::Jump) {
+                direction += Vec3::Y;
+            }
+            if actions.pressed(Action::Crouch) {
+                direction -= Vec3::Y;
+            }
+            let mut speed = spectator.speed;
+            if actions.pressed(Action::Sprint) {
+                speed *= spectator.fast_multiplier;
+            }
+            // noclip, nothing stops the camera
+            let position = spectator.position;
+            Transform::from_translation(
+                position + direction.clamp_length_max(1.) * speed * time.delta_seconds(),
+            )
+            .with_rotation(rotation)
+        }
+    };
+    // switching back to free fly carries on from wherever the camera was
+    spectator.position = view.translation;
+
+    // the view is still parented to the player, so place it relative to the player's transform
+    for (mut transform, parent) in view_query.iter_mut() {
+        if let Ok((parent_transform, _)) = target_query.get(parent.get()) {
+            *transform = GlobalTransform::from(view).reparented_to(parent_transform);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use crate::fps_movement::FPSMovement;
+
+#[derive(Component)]
+pub struct StaminaBar {}
+
+#[derive(Component)]
+pub struct StaminaBarFill {}
+
+pub fn spawn_stamina_bar(parent: &mut ChildBuilder) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(220.0), Val::Px(8.0)),
+                    margin: UiRect::bottom(Val::Px(52.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.6).into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            StaminaBar {},
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.9, 0.9, 0.9).into(),
+                    ..default()
+                },
+                StaminaBarFill {},
+            ));
+        });
+}
+
+pub fn update_stamina_bar(
+    movement_query: Query<&FPSMovement>,
+    mut bar_query: Query<&mut Visibility, With<StaminaBar>>,
+    mut fill_query: Query<(&mut Style, &mut BackgroundColor), With<StaminaBarFill>>,
+) {
+    let Ok(movement) = movement_query.get_single() else {
+        return;
+    };
+    let max = movement.stamina_settings.max;
+    let fraction = if max > 0. {
+        (movement.stamina / max).clamp(0., 1.)
+    } else {
+        1.
+    };
+    // only shown while stamina is being used or refilled
+    if let Ok(mut visibility) = bar_query.get_single_mut() {
+        let target = if fraction < 1. {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+    if let Ok((mut style, mut color)) = fill_query.get_single_mut() {
+        style.size.width = Val::Percent(fraction * 100.);
+        *color = if movement.exhausted {
+            Color::rgb(0.85, 0.25, 0.2).into()
+        } else {
+            Color::rgb(0.9, 0.9, 0.9).into()
+        };
+    }
+}
@@ -0,0 +1,206 @@
+use std::f32::consts::PI;
+
+use bevy::{prelude::*, render::view::NoFrustumCulling};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    camera_effects::CameraEffects,
+    fps_camera::{view_transform, FPSCamera, PlayerView},
+    fps_movement::{FPSMovement, MovementState},
+    gun_control::GunController,
+    spectator::{camera_arm_length, Spectator, SpectatorMode},
+    AnimationEntityLink, EnemyAnimations,
+};
+
+#[derive(Component)]
+pub struct ThirdPersonCamera {
+    pub enabled: bool,
+    pub distance: f32,
+    // where the arm starts relative to the first person view, x puts the player off to one side
+    pub shoulder_offset: Vec3,
+    pub arm_radius: f32,
+    // meters per second the arm grows back after a wall stops pushing it in
+    pub return_speed: f32,
+    pub arm_length: f32,
+    // world position of the character's weapon, shots start here while in third person
+    pub muzzle: Option<Vec3>,
+}
+
+// the visible body of the player, kept separate so it only follows the look yaw
+#[derive(Component)]
+pub struct PlayerModel {
+    pub gun: Option<Entity>,
+    pub stride_phase: f32,
+}
+
+// the camera picks what is aimed at, the shot then travels there from the weapon and can be
+// stopped by anything in between
+pub fn weapon_hit(
+    rapier_context: &RapierContext,
+    muzzle: Vec3,
+    aim_point: Vec3,
+    filter: QueryFilter,
+) -> Option<(Entity, RayIntersection)> {
+    let to_aim = aim_point - muzzle;
+    let distance = to_aim.length();
+    if distance < 0.001 {
+        return None;
+    }
+    // a little past the aim point so the surface seen through the crosshair is still hit
+    rapier_context.cast_ray_and_get_normal(muzzle, to_aim / distance, distance + 0.05, true, filter)
+}
+
+pub fn update_third_person_camera(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut camera_query: Query<
+        (
+            &GlobalTransform,
+            &FPSCamera,
+            Option<&CameraEffects>,
+            &mut ThirdPersonCamera,
+        ),
+        Without<PlayerView>,
+    >,
+    mut view_query: Query<&mut Transform, With<PlayerView>>,
+) {
+    let Ok((player_transform, camera, effects, mut third_person)) =
+        camera_query.get_single_mut()
+    else {
+        return;
+    };
+    if !third_person.enabled {
+        return;
+    }
+    let view = view_transform(camera, effects);
+    let pivot = view.translation + view.rotation * third_person.shoulder_offset;
+    let back = view.rotation * Vec3::Z;
+
+    let world_pivot = player_transform.transform_point(pivot);
+    let world_back = player_transform.affine().transform_vector3(back).normalize_or_zero();
+    let free_length = camera_arm_length(
+        &rapier_context,
+        world_pivot,
+        world_back,
+        third_person.distance,
+        third_person.arm_radius,
+        QueryFilter::only_fixed().exclude_sensors(),
+    );
+    // pull in straight away so walls never get between the camera and the player,
+    // but ease back out so the view doesn't pop when passing a corner
+    let grown = third_person.arm_length + third_person.return_speed * time.delta_seconds();
+    third_person.arm_length = f32::min(free_length, grown);
+
+    for mut transform in view_query.iter_mut() {
+        *transform = Transform::from_translation(pivot + back * third_person.arm_length)
+            .with_rotation(view.rotation);
+    }
+}
+
+pub fn update_player_model(
+    mut commands: Commands,
+    time: Res<Time>,
+    spectator: Res<Spectator>,
+    enemy_animations: Res<EnemyAnimations>,
+    mut player_query: Query<(Entity, &Transform, &FPSCamera, &FPSMovement, &mut ThirdPersonCamera)>,
+    mut model_query: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &mut PlayerModel,
+            Entity,
+            Option<&AnimationEntityLink>,
+        ),
+        Without<FPSMovement>,
+    >,
+    mut gun_query: Query<&mut Visibility, (With<GunController>, Without<PlayerModel>)>,
+    mut animation_player_query: Query<&mut AnimationPlayer>,
+    children_query: Query<&Children>,
+    name_query: Query<&Name>,
+    global_query: Query<&GlobalTransform>,
+) {
+    let Ok((player, player_transform, camera, movement, mut third_person)) =
+        player_query.get_single_mut()
+    else {
+        return;
+    };
+    // the body is drawn whenever the player isn't looking out of its own eyes
+    let watching_own_eyes = spectator.mode == SpectatorMode::FirstPerson
+        && spectator.target == Some(player);
+    let show_body = if spectator.active {
+        !watching_own_eyes
+    } else {
+        third_person.enabled
+    };
+
+    third_person.muzzle = None;
+    for (mut transform, mut visibility, mut model, entity, animation_link) in
+        model_query.iter_mut()
+    {
+        // person.glb only has a standing pose and a death, so walking is the standing pose
+        // bounced with each step and leant into the direction of travel
+        let horizontal_velocity = Vec3::new(movement.velocity.x, 0., movement.velocity.z);
+        let speed = horizontal_velocity.length();
+        let moving = movement.grounded
+            && speed > 0.5
+            && !matches!(movement.state, MovementState::Climbing | MovementState::Sliding);
+        let mut stride = Vec2::ZERO;
+        if moving {
+            model.stride_phase += speed * 1.4 * PI * time.delta_seconds();
+            let pace = (speed / movement.speed.max(0.01)).min(1.5);
+            stride = Vec2::new(
+                f32::abs(f32::sin(model.stride_phase)) * 0.05 * pace,
+                f32::min(speed * 0.012, 0.2),
+            );
+        } else {
+            model.stride_phase = 0.;
+        }
+        let lean_axis = Vec3::Y.cross(horizontal_velocity).normalize_or_zero();
+        let lean = if lean_axis == Vec3::ZERO {
+            Quat::IDENTITY
+        } else {
+            Quat::from_axis_angle(lean_axis, stride.y)
+        };
+
+        let feet = movement.collider_half_height + movement.collider_radius;
+        transform.translation = player_transform.translation - Vec3::Y * (feet - stride.x);
+        // the model is authored facing +z while the camera looks down -z
+        transform.rotation = lean * Quat::from_rotation_y(camera.rotation.y + PI);
+        *visibility = if show_body {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        // skinned meshes get culled by their bind pose bounds, same as the enemies
+        if model.gun.is_none() {
+            for descendant in children_query.iter_descendants(entity) {
+                commands.entity(descendant).insert(NoFrustumCulling);
+                if name_query.get(descendant).map_or(false, |name| name.as_str() == "Gun") {
+                    model.gun = Some(descendant);
+                }
+            }
+        }
+        if third_person.enabled && !spectator.active {
+            third_person.muzzle = model
+                .gun
+                .and_then(|gun| global_query.get(gun).ok())
+                .map(|gun_transform| gun_transform.translation());
+        }
+
+        // the standing pose, moving or not, the stride above does the walking
+        if let Some(animation_link) = animation_link {
+            if let Ok(mut animation_player) = animation_player_query.get_mut(animation_link.0) {
+                animation_player.play(enemy_animations.0[0].clone_weak()).repeat();
+            }
+        }
+    }
+
+    for mut visibility in gun_query.iter_mut() {
+        *visibility = if show_body {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+}
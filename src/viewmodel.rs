@@ -0,0 +1,66 @@
+use bevy::{
+    core_pipeline::tonemapping::Tonemapping,
+    prelude::*,
+    render::{camera::CameraOutputMode, view::RenderLayers},
+};
+
+use crate::{fps_camera::PlayerView, gun_control::GunController};
+
+pub const VIEWMODEL_LAYER: u8 = 1;
+
+// draws only the gun, on top of the world camera and with its own fov. it sits on the player
+// view so the gun keeps its real world position and is lit and shadowed like the scene around it
+#[derive(Component)]
+pub struct ViewmodelCamera {}
+
+// the gun scene spawns its meshes a few frames after the entity, so new descendants are
+// moved onto the viewmodel layer as they appear
+pub fn assign_viewmodel_layer(
+    mut commands: Commands,
+    gun_query: Query<Entity, With<GunController>>,
+    children_query: Query<&Children>,
+    layer_query: Query<&RenderLayers>,
+) {
+    for gun in gun_query.iter() {
+        for entity in std::iter::once(gun).chain(children_query.iter_descendants(gun)) {
+            if layer_query.get(entity).is_err() {
+                commands
+                    .entity(entity)
+                    .insert(RenderLayers::layer(VIEWMODEL_LAYER));
+            }
+        }
+    }
+}
+
+pub fn update_viewmodel_camera(
+    gun_query: Query<&Visibility, With<GunController>>,
+    mut viewmodel_query: Query<&mut Camera, With<ViewmodelCamera>>,
+    mut view_query: Query<
+        (&mut Camera, &mut Tonemapping),
+        (With<PlayerView>, Without<ViewmodelCamera>),
+    >,
+) {
+    let viewmodel_active = gun_query
+        .iter()
+        .any(|visibility| *visibility != Visibility::Hidden);
+    for mut camera in viewmodel_query.iter_mut() {
+        if camera.is_active != viewmodel_active {
+            camera.is_active = viewmodel_active;
+        }
+    }
+    // while the viewmodel draws, the world camera leaves its hdr output for it to draw over
+    // and tonemap once, otherwise the world camera finishes the frame itself
+    let (output_mode, tonemapping) = if viewmodel_active {
+        (CameraOutputMode::Skip, Tonemapping::None)
+    } else {
+        (CameraOutputMode::default(), Tonemapping::TonyMcMapface)
+    };
+    for (mut camera, mut view_tonemapping) in view_query.iter_mut() {
+        if matches!(camera.output_mode, CameraOutputMode::Skip) != viewmodel_active {
+            camera.output_mode = output_mode;
+        }
+        if *view_tonemapping != tonemapping {
+            *view_tonemapping = tonemapping;
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config_file::{asset_file_path, load_asset_toml, save_toml},
+    recoil::RecoilSettings,
+    weapon_sway::WeaponSwaySettings,
+};
+
+// stored as f64 and rounded on save so the file reads 0.007 instead of 0.007000000216
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct WeaponDefinition {
+    // where each shot of a spray lands relative to the first, x to the right and y up in radians
+    pub spray_pattern: Vec<[f64; 2]>,
+    pub recoil: RecoilSettings,
+    pub sway: WeaponSwaySettings,
+}
+
+impl WeaponDefinition {
+    pub fn spray_pattern(&self) -> Vec<Vec2> {
+        self.spray_pattern
+            .iter()
+            .map(|point| Vec2::new(point[0] as f32, point[1] as f32))
+            .collect()
+    }
+    pub fn set_spray_pattern(&mut self, spray_pattern: &[Vec2]) {
+        let round = |value: f32| (value as f64 * 10000.).round() / 10000.;
+        self.spray_pattern = spray_pattern
+            .iter()
+            .map(|point| [round(point.x), round(point.y)])
+            .collect();
+    }
+}
+
+pub fn weapon_definition_file(name: &str) -> String {
+    format!("weapons/{}.toml", name)
+}
+
+pub fn weapon_definition_path(name: &str) -> PathBuf {
+    asset_file_path(&weapon_definition_file(name))
+}
+
+pub fn load_weapon_definition(name: &str) -> Result<WeaponDefinition, String> {
+    let file = weapon_definition_file(name);
+    let definition: WeaponDefinition = load_asset_toml(&file)?;
+    // without a pattern the gun would fire with no recoil at all
+    if definition.spray_pattern.is_empty() {
+        return Err(format!("{} has no spray pattern", file));
+    }
+    definition
+        .recoil
+        .validate()
+        .map_err(|reason| format!("{}: {}", file, reason))?;
+    Ok(definition)
+}
+
+// only the pattern is written, everything else keeps the value it was given in the file. going
+// through the f32 fields would save 0.012 as 0.012000000104308128. a broken file is replaced
+pub fn save_spray_pattern(name: &str, spray_pattern: &[Vec2]) {
+    let mut definition = WeaponDefinition::default();
+    definition.set_spray_pattern(spray_pattern);
+    let mut table: toml::Table =
+        load_asset_toml(&weapon_definition_file(name)).unwrap_or_default();
+    match toml::Value::try_from(&definition.spray_pattern) {
+        Ok(pattern) => table.insert("spray_pattern".to_string(), pattern),
+        Err(err) => {
+            warn!("failed to serialize the spray pattern for {}: {}", name, err);
+            return;
+        }
+    };
+    save_toml(&weapon_definition_path(name), &table);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rifle_file_sets_its_sway() {
+        let definition: WeaponDefinition =
+            toml::from_str(include_str!("../assets/weapons/rifle.toml")).unwrap();
+        assert_eq!(definition.spray_pattern.len(), 25);
+        assert_eq!(definition.sway.position_spring.stiffness, 180.);
+        assert_eq!(definition.sway.sprint_offset, Vec3::new(-0.05, -0.12, 0.02));
+        assert_eq!(definition.recoil.view_recovery.time, 0.35);
+        assert!(definition.recoil.validate().is_ok());
+    }
+
+    #[test]
+    fn bad_recoil_values_are_rejected() {
+        let definition: WeaponDefinition =
+            toml::from_str("[recoil]\nview_kick = -0.5\n").unwrap();
+        assert!(definition.recoil.validate().is_err());
+        let definition: WeaponDefinition = toml::from_str(
+            "[recoil.gun_recovery]\ndelay = 0.0\ntime = nan\ncurve = \"Linear\"\n",
+        )
+        .unwrap();
+        assert!(definition.recoil.validate().is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fps_camera::FPSCamera,
+    fps_movement::{ease_bob_intensity, horizontal_speed, FPSMovement, PlayerLanded},
+    gun_control::GunController,
+};
+
+// long frames are split up so stiff springs don't overshoot and blow up
+const MAX_SPRING_STEP: f32 = 1. / 120.;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Spring {
+    pub stiffness: f32,
+    pub damping: f32,
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SpringState {
+    pub position: Vec3,
+    pub velocity: Vec3,
+}
+
+impl SpringState {
+    pub fn step(&mut self, target: Vec3, spring: Spring, delta_seconds: f32) {
+        let steps = f32::max((delta_seconds / MAX_SPRING_STEP).ceil(), 1.);
+        let dt = delta_seconds / steps;
+        for _ in 0..steps as u32 {
+            let acceleration =
+                (target - self.position) * spring.stiffness - self.velocity * spring.damping;
+            self.velocity += acceleration * dt;
+            self.position += self.velocity * dt;
+        }
+    }
+}
+
+// how a gun moves in the hands, each weapon gets its own so heavy guns can feel heavy. a weapon
+// file only needs the values it changes
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeaponSwaySettings {
+    pub position_spring: Spring,
+    pub rotation_spring: Spring,
+    // meters the gun trails per radian per second of turning, x for yaw and y for pitch
+    pub look_lag: Vec2,
+    // radians the gun trails per radian per second of turning
+    pub look_rotation_lag: f32,
+    pub max_look_lag: f32,
+    pub max_look_rotation: f32,
+    pub bob_amplitude: Vec2,
+    // footsteps per meter travelled
+    pub bob_frequency: f32,
+    // roll in radians per meter per second of strafing
+    pub strafe_tilt: f32,
+    pub max_strafe_tilt: f32,
+    // downward kick in meters per second per meter per second of landing speed
+    pub landing_kick: f32,
+    pub sprint_offset: Vec3,
+    pub sprint_pitch: f32,
+    // sway is scaled by this while aiming down sights
+    pub ads_multiplier: f32,
+}
+
+impl Default for WeaponSwaySettings {
+    fn default() -> Self {
+        WeaponSwaySettings {
+            position_spring: Spring {
+                stiffness: 180.,
+                damping: 18.,
+            },
+            rotation_spring: Spring {
+                stiffness: 220.,
+                damping: 20.,
+            },
+            look_lag: Vec2::new(0.012, 0.01),
+            look_rotation_lag: 0.02,
+            max_look_lag: 0.06,
+            max_look_rotation: 5_f32.to_radians(),
+            bob_amplitude: Vec2::new(0.012, 0.008),
+            bob_frequency: 0.7,
+            strafe_tilt: 0.02,
+            max_strafe_tilt: 6_f32.to_radians(),
+            landing_kick: 0.08,
+            sprint_offset: Vec3::new(-0.05, -0.12, 0.02),
+            sprint_pitch: -0.6,
+            ads_multiplier: 0.25,
+        }
+    }
+}
+
+// in camera space, rotation is pitch, yaw and roll in radians
+#[derive(Clone, Copy, Default, Debug)]
+pub struct WeaponSway {
+    pub position: SpringState,
+    pub rotation: SpringState,
+    pub bob_phase: f32,
+    pub bob_intensity: f32,
+    pub translation_offset: Vec3,
+    pub rotation_offset: Vec3,
+}
+
+pub fn update_weapon_sway(
+    time: Res<Time>,
+    mut landed_events: EventReader<PlayerLanded>,
+    mut gun_query: Query<&mut GunController>,
+    player_query: Query<(&FPSCamera, &FPSMovement)>,
+) {
+    let delta_seconds = time.delta_seconds();
+    let impact_speed = landed_events
+        .iter()
+        .map(|landed| landed.impact_speed)
+        .fold(0., f32::max);
+    if delta_seconds <= 0. {
+        return;
+    }
+    let Ok((camera, movement)) = player_query.get_single() else {
+        return;
+    };
+    for mut gun_controller in gun_query.iter_mut() {
+        let gun_controller = gun_controller.as_mut();
+        let settings = &gun_controller.sway_settings;
+        let sway = &mut gun_controller.sway;
+        let steadiness = if gun_controller.aiming_down_sights {
+            settings.ads_multiplier
+        } else {
+            1.
+        };
+
+        let look_speed = camera.look_speed;
+        let max_lag = settings.max_look_lag;
+        // turning right leaves the gun behind on the left, looking up leaves it low
+        let mut target_position = Vec3::new(
+            (look_speed.y * settings.look_lag.x).clamp(-max_lag, max_lag),
+            (-look_speed.x * settings.look_lag.y).clamp(-max_lag, max_lag),
+            0.,
+        ) * steadiness;
+        let max_rotation = settings.max_look_rotation;
+        let mut target_rotation = Vec3::new(
+            (-look_speed.x * settings.look_rotation_lag).clamp(-max_rotation, max_rotation),
+            (-look_speed.y * settings.look_rotation_lag).clamp(-max_rotation, max_rotation),
+            0.,
+        ) * steadiness;
+
+        let right = Quat::from_rotation_y(camera.rotation.y) * Vec3::X;
+        let strafe_speed = movement.velocity.dot(right);
+        target_rotation.z = (-strafe_speed * settings.strafe_tilt)
+            .clamp(-settings.max_strafe_tilt, settings.max_strafe_tilt)
+            * steadiness;
+
+        if gun_controller.lowered {
+            target_position += settings.sprint_offset;
+            target_rotation.x += settings.sprint_pitch;
+        }
+        if impact_speed > 0. {
+            sway.position.velocity.y -= impact_speed * settings.landing_kick;
+        }
+
+        sway.position.step(target_position, settings.position_spring, delta_seconds);
+        sway.rotation.step(target_rotation, settings.rotation_spring, delta_seconds);
+
+        // the bob follows footsteps exactly, so it's added on top of the springs
+        sway.bob_intensity =
+            ease_bob_intensity(sway.bob_intensity, movement, steadiness, delta_seconds);
+        sway.bob_phase += horizontal_speed(movement)
+            * settings.bob_frequency
+            * std::f32::consts::TAU
+            * delta_seconds;
+        let bob = Vec3::new(
+            f32::sin(sway.bob_phase * 0.5) * settings.bob_amplitude.x,
+            -f32::abs(f32::sin(sway.bob_phase * 0.5)) * settings.bob_amplitude.y,
+            0.,
+        ) * sway.bob_intensity;
+
+        sway.translation_offset = sway.position.position + bob;
+        sway.rotation_offset = sway.rotation.position;
+    }
+}